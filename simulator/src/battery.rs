@@ -0,0 +1,115 @@
+use crate::vehicle::VehicleClass;
+
+const JOULES_PER_KWH: f32 = 3_600_000.0;
+
+/// The traction battery of an electric vehicle
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Battery {
+    capacity: f32, // kWh
+    energy: f32,   // kWh
+    /// Below this state of charge the driver detours to a charging station
+    low_charge: f32,
+    /// Charging stops at this state of charge
+    charged: f32,
+}
+
+impl Battery {
+    /// The state of charge is the fraction of the capacity that's in the battery
+    pub fn new(capacity: f32, state_of_charge: f32) -> Self {
+        Self {
+            capacity,
+            energy: capacity * state_of_charge.clamp(0.0, 1.0),
+            low_charge: 0.2,
+            charged: 0.8,
+        }
+    }
+
+    /// Sets the states of charge at which the driver looks for a charger and stops charging
+    pub fn with_charging_thresholds(mut self, low_charge: f32, charged: f32) -> Self {
+        self.low_charge = low_charge;
+        self.charged = charged;
+        self
+    }
+
+    /// kWh
+    pub fn capacity(&self) -> f32 {
+        self.capacity
+    }
+
+    /// The energy that's left in kWh
+    pub fn energy(&self) -> f32 {
+        self.energy
+    }
+
+    pub fn state_of_charge(&self) -> f32 {
+        self.energy / self.capacity
+    }
+
+    pub fn is_low(&self) -> bool {
+        self.state_of_charge() < self.low_charge
+    }
+
+    pub fn is_charged(&self) -> bool {
+        self.state_of_charge() >= self.charged
+    }
+
+    pub fn is_depleted(&self) -> bool {
+        self.energy <= 0.0
+    }
+
+    /// Takes energy in kWh out of the battery. Negative energy charges it.
+    pub(crate) fn discharge(&mut self, energy: f32) {
+        self.energy = (self.energy - energy).clamp(0.0, self.capacity);
+    }
+}
+
+/// Calculates the power the battery delivers to drive a vehicle
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct EnergyModel {
+    /// From the battery to the wheels
+    pub drivetrain_efficiency: f32,
+    /// The part of the braking energy that flows back into the battery
+    pub regeneration_efficiency: f32,
+    pub max_regeneration_power: f32, // W
+    /// Climate control, lights, etc.
+    pub auxiliary_power: f32, // W
+}
+
+impl EnergyModel {
+    /// The power in W drawn from the battery. Negative when regenerative braking charges it.
+    /// The grade is the sine of the slope, negative when going downhill.
+    pub fn power(&self, class: VehicleClass, speed: f32, acceleration: f32, grade: f32) -> f32 {
+        let wheel_power = class.road_load_force(speed, acceleration, grade) * speed;
+
+        let battery_power = if wheel_power >= 0.0 {
+            wheel_power / self.drivetrain_efficiency
+        } else {
+            (wheel_power * self.regeneration_efficiency).max(-self.max_regeneration_power)
+        };
+
+        battery_power + self.auxiliary_power
+    }
+
+    /// The energy in kWh drawn from the battery during the time step
+    pub fn energy(
+        &self,
+        class: VehicleClass,
+        speed: f32,
+        acceleration: f32,
+        grade: f32,
+        delta_time: f32,
+    ) -> f32 {
+        self.power(class, speed, acceleration, grade) * delta_time / JOULES_PER_KWH
+    }
+}
+
+impl Default for EnergyModel {
+    fn default() -> Self {
+        Self {
+            drivetrain_efficiency: 0.9,
+            regeneration_efficiency: 0.65,
+            max_regeneration_power: 60_000.0,
+            auxiliary_power: 500.0,
+        }
+    }
+}
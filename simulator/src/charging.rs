@@ -0,0 +1,64 @@
+use crate::user::RoadUser;
+
+/// A place at a node where electric vehicles leave the network to charge
+#[derive(Debug, Clone)]
+pub struct ChargingStation {
+    id: u32,
+    node: u32,
+    power: f32, // kW per plug
+    plugs: u32,
+    occupied: u32,
+}
+
+impl ChargingStation {
+    pub fn new(id: u32, node: u32, power: f32, plugs: u32) -> Self {
+        Self {
+            id,
+            node,
+            power,
+            plugs,
+            occupied: 0,
+        }
+    }
+
+    pub fn id(&self) -> u32 {
+        self.id
+    }
+
+    pub fn node(&self) -> u32 {
+        self.node
+    }
+
+    /// kW per plug
+    pub fn power(&self) -> f32 {
+        self.power
+    }
+
+    pub fn plugs(&self) -> u32 {
+        self.plugs
+    }
+
+    pub fn occupied(&self) -> u32 {
+        self.occupied
+    }
+
+    pub fn free_plugs(&self) -> u32 {
+        self.plugs.saturating_sub(self.occupied)
+    }
+
+    pub(crate) fn plug_in(&mut self) {
+        self.occupied += 1;
+    }
+
+    pub(crate) fn unplug(&mut self) {
+        self.occupied = self.occupied.saturating_sub(1);
+    }
+}
+
+/// An electric vehicle at a charging station, waiting for a free plug or charging
+#[derive(Debug, Clone)]
+pub(crate) struct ChargingVehicle {
+    pub user: RoadUser,
+    pub station: u32,
+    pub plugged_in: bool,
+}
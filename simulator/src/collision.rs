@@ -0,0 +1,153 @@
+use nalgebra::{Point2, Point3, Vector2, Vector3};
+
+use crate::user::RoadUser;
+
+/// Vehicles further apart vertically than this are on different levels, e.g. on a bridge
+const MAX_VERTICAL_SEPARATION: f32 = 2.0; // m
+
+/// What the simulator does with road users that collided
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum CollisionResponse {
+    /// Only emit the collision event
+    #[default]
+    Report,
+    /// Bring the involved road users to an immediate standstill where they are
+    Halt,
+    /// Take the involved road users out of the simulation
+    Remove,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Collision {
+    pub time: f32,
+    pub involved: [CollisionParty; 2],
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CollisionParty {
+    pub id: u32,
+    pub location: Point3<f32>,
+    pub speed: f32,
+}
+
+impl CollisionParty {
+    fn of(user: &RoadUser) -> Self {
+        Self {
+            id: user.id,
+            location: user.location(),
+            speed: user.current_speed(),
+        }
+    }
+}
+
+/// The rectangle a road user occupies on the ground plane
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Footprint {
+    center: Point3<f32>,
+    forward: Vector2<f32>,
+    length: f32, // m
+    width: f32,  // m
+}
+
+impl Footprint {
+    pub fn new(center: Point3<f32>, direction: Vector3<f32>, length: f32, width: f32) -> Self {
+        Self {
+            center,
+            forward: direction
+                .xy()
+                .try_normalize(f32::EPSILON)
+                .unwrap_or(Vector2::x()),
+            length,
+            width,
+        }
+    }
+
+    pub fn of(user: &RoadUser) -> Self {
+        Self::new(
+            user.location(),
+            user.current_direction(),
+            user.length(),
+            user.width(),
+        )
+    }
+
+    pub fn corners(&self) -> [Point2<f32>; 4] {
+        let center = self.center.xy();
+        let half_forward = self.forward * self.length / 2.0;
+        let half_side = Vector2::new(-self.forward.y, self.forward.x) * self.width / 2.0;
+
+        [
+            center + half_forward + half_side,
+            center + half_forward - half_side,
+            center - half_forward - half_side,
+            center - half_forward + half_side,
+        ]
+    }
+
+    pub fn contains(&self, point: &Point3<f32>) -> bool {
+        if (self.center.z - point.z).abs() > MAX_VERTICAL_SEPARATION {
+            return false;
+        }
+
+        let offset = point.xy() - self.center.xy();
+        let side = Vector2::new(-self.forward.y, self.forward.x);
+
+        offset.dot(&self.forward).abs() <= self.length / 2.0
+            && offset.dot(&side).abs() <= self.width / 2.0
+    }
+
+    /// Separating axis test of the two rectangles
+    pub fn overlaps(&self, other: &Footprint) -> bool {
+        if (self.center.z - other.center.z).abs() > MAX_VERTICAL_SEPARATION {
+            return false;
+        }
+
+        let reach = (self.length.hypot(self.width) + other.length.hypot(other.width)) / 2.0;
+        if (self.center.xy() - other.center.xy()).magnitude() > reach {
+            return false;
+        }
+
+        let own_corners = self.corners();
+        let other_corners = other.corners();
+
+        [
+            self.forward,
+            Vector2::new(-self.forward.y, self.forward.x),
+            other.forward,
+            Vector2::new(-other.forward.y, other.forward.x),
+        ]
+        .iter()
+        .all(|axis| {
+            let (own_min, own_max) = project(&own_corners, axis);
+            let (other_min, other_max) = project(&other_corners, axis);
+
+            own_min < other_max && other_min < own_max
+        })
+    }
+}
+
+fn project(corners: &[Point2<f32>; 4], axis: &Vector2<f32>) -> (f32, f32) {
+    corners
+        .iter()
+        .map(|corner| corner.coords.dot(axis))
+        .fold((f32::INFINITY, f32::NEG_INFINITY), |(min, max), value| {
+            (min.min(value), max.max(value))
+        })
+}
+
+/// All pairs of road users whose footprints overlap, given as indices into the slice
+pub(crate) fn find_overlapping(users: &[RoadUser]) -> Vec<(usize, usize)> {
+    let footprints = users.iter().map(Footprint::of).collect::<Vec<_>>();
+
+    (0..footprints.len())
+        .flat_map(|a| (a + 1..footprints.len()).map(move |b| (a, b)))
+        .filter(|(a, b)| footprints[*a].overlaps(&footprints[*b]))
+        .collect()
+}
+
+pub(crate) fn collision_between(time: f32, a: &RoadUser, b: &RoadUser) -> Collision {
+    Collision {
+        time,
+        involved: [CollisionParty::of(a), CollisionParty::of(b)],
+    }
+}
@@ -0,0 +1,103 @@
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+use crate::{user::RoadUser, vehicle::VehicleClass};
+
+/// A stream of vehicles from an origin to a destination. The vehicles depart at random,
+/// so the gaps between them follow an exponential distribution. When the origin is
+/// blocked, they wait until there's room to enter the network.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TrafficDemand {
+    id: u32,
+    origin: u32,
+    destination: u32,
+    rate: f32, // vehicles/h
+    #[serde(default)]
+    vehicle_class: VehicleClass,
+    #[serde(skip)]
+    next_departure: Option<f32>,
+    #[serde(skip)]
+    waiting: u32,
+}
+
+impl TrafficDemand {
+    pub fn new(id: u32, origin: u32, destination: u32, rate: f32) -> Self {
+        Self {
+            id,
+            origin,
+            destination,
+            rate,
+            vehicle_class: VehicleClass::default(),
+            next_departure: None,
+            waiting: 0,
+        }
+    }
+
+    pub fn with_vehicle_class(mut self, vehicle_class: VehicleClass) -> Self {
+        self.vehicle_class = vehicle_class;
+        self
+    }
+
+    pub fn id(&self) -> u32 {
+        self.id
+    }
+
+    pub fn origin(&self) -> u32 {
+        self.origin
+    }
+
+    pub fn destination(&self) -> u32 {
+        self.destination
+    }
+
+    /// vehicles/h
+    pub fn rate(&self) -> f32 {
+        self.rate
+    }
+
+    pub fn vehicle_class(&self) -> VehicleClass {
+        self.vehicle_class
+    }
+
+    /// The vehicles that should have departed, but couldn't enter the network yet
+    pub fn waiting_vehicles(&self) -> u32 {
+        self.waiting
+    }
+
+    /// The departures are drawn again with the new rate
+    pub fn set_rate(&mut self, rate: f32) {
+        self.rate = rate;
+        self.next_departure = None;
+    }
+
+    /// Adds the departures that fall in the window `[from, to)` to the waiting vehicles
+    pub(crate) fn queue_departures(&mut self, from: f32, to: f32, rng: &mut impl Rng) {
+        if self.rate <= 0.0 {
+            self.next_departure = None;
+            return;
+        }
+
+        let mean_gap = 3600.0 / self.rate;
+        let mut sample_gap = || -mean_gap * (1.0 - rng.gen::<f32>()).ln();
+
+        let mut next_departure = *self
+            .next_departure
+            .get_or_insert_with(|| from + sample_gap());
+        while next_departure < to {
+            self.waiting += 1;
+            next_departure += sample_gap();
+        }
+        self.next_departure = Some(next_departure);
+    }
+
+    pub(crate) fn depart(&mut self) {
+        self.waiting = self.waiting.saturating_sub(1);
+    }
+}
+
+/// A prepared road user that enters the network at the given time
+#[derive(Debug, Clone)]
+pub(crate) struct ScheduledDeparture {
+    pub time: f32, // s
+    pub user: RoadUser,
+}
@@ -0,0 +1,95 @@
+use std::collections::{HashSet, VecDeque};
+
+use crate::{collision::Footprint, road::RoadNetwork, user::RoadUser};
+
+/// A loop detector at a node. It measures the occupancy (the fraction of time a vehicle is
+/// above the detector) over a sliding time window and counts the passing vehicles.
+#[derive(Debug, Clone)]
+pub struct Detector {
+    id: u32,
+    node: u32,
+    window: f32, // s
+
+    samples: VecDeque<(f32, f32, bool)>, // (time, duration, occupied)
+    present: HashSet<u32>,
+    vehicle_count: u32,
+}
+
+impl Detector {
+    pub fn new(id: u32, node: u32, window: f32) -> Self {
+        Self {
+            id,
+            node,
+            window,
+            samples: VecDeque::new(),
+            present: HashSet::new(),
+            vehicle_count: 0,
+        }
+    }
+
+    pub fn id(&self) -> u32 {
+        self.id
+    }
+
+    pub fn node(&self) -> u32 {
+        self.node
+    }
+
+    /// Between 0 and 1
+    pub fn occupancy(&self) -> f32 {
+        let (occupied_time, total_time) = self.samples.iter().fold(
+            (0.0, 0.0),
+            |(occupied_time, total_time), (_, duration, occupied)| {
+                (
+                    occupied_time + if *occupied { *duration } else { 0.0 },
+                    total_time + duration,
+                )
+            },
+        );
+
+        if total_time > 0.0 {
+            occupied_time / total_time
+        } else {
+            0.0
+        }
+    }
+
+    /// The amount of vehicles that have driven onto the detector since the start
+    pub fn vehicle_count(&self) -> u32 {
+        self.vehicle_count
+    }
+
+    pub(crate) fn tick(
+        &mut self,
+        current_time: f32,
+        delta_time: f32,
+        network: &RoadNetwork,
+        road_users: &[RoadUser],
+    ) {
+        let location = network.find_node(self.node).location();
+
+        let present = road_users
+            .iter()
+            .filter(|user| Footprint::of(user).contains(&location))
+            .map(|user| user.id)
+            .collect::<HashSet<_>>();
+
+        self.vehicle_count += present.difference(&self.present).count() as u32;
+        self.samples
+            .push_back((current_time, delta_time, !present.is_empty()));
+        self.present = present;
+
+        while self
+            .samples
+            .front()
+            .is_some_and(|(time, _, _)| *time < current_time - self.window)
+        {
+            self.samples.pop_front();
+        }
+    }
+}
+
+/// Finds the detector with the given id
+pub(crate) fn find_detector(detectors: &[Detector], id: u32) -> Option<&Detector> {
+    detectors.iter().find(|detector| detector.id == id)
+}
@@ -0,0 +1,45 @@
+use std::io::{self, Write};
+
+use crate::road::RoadNetwork;
+
+/// The topology of the network as a Graphviz digraph, for checking connectivity.
+/// Next nodes are solid edges labelled with the speed limit in km/h of the node they lead to,
+/// adjacent lanes are dashed edges without an arrow.
+pub fn write_network(network: &RoadNetwork, mut writer: impl Write) -> io::Result<()> {
+    let mut node_ids = network.all_node_ids().collect::<Vec<_>>();
+    node_ids.sort();
+
+    writeln!(writer, "digraph road_network {{")?;
+
+    for id in node_ids.iter() {
+        writeln!(writer, "    {id};")?;
+    }
+
+    for id in node_ids.iter() {
+        let node = network.find_node(*id);
+        for next in node.next_node_ids() {
+            writeln!(
+                writer,
+                "    {id} -> {next} [label=\"{:.0}\"];",
+                network.find_node(*next).max_speed() * 3.6
+            )?;
+        }
+    }
+
+    for id in node_ids.iter() {
+        let node = network.find_node(*id);
+        for (side, adjacent) in [
+            ("right", node.adjacent_node_right_id()),
+            ("left", node.adjacent_node_left_id()),
+        ] {
+            if let Some(adjacent) = adjacent {
+                writeln!(
+                    writer,
+                    "    {id} -> {adjacent} [style=dashed, arrowhead=none, label=\"{side}\"];"
+                )?;
+            }
+        }
+    }
+
+    writeln!(writer, "}}")
+}
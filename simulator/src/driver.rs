@@ -0,0 +1,144 @@
+use rand::Rng;
+
+/// The personal preferences of the driver of a road user
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DriverBehaviour {
+    /// Multiplied with `Node::max_speed` to get the speed the driver wants to drive at
+    pub desired_speed_factor: f32,
+    /// Multiplied with the acceleration of the vehicle
+    pub acceleration_factor: f32,
+    /// The time gap the driver keeps to the vehicle in front (s)
+    pub time_headway: f32,
+    /// The fraction of the vehicle's deceleration the driver is willing to use to stop for an
+    /// orange light. If stopping would take more, the driver continues through.
+    pub orange_deceleration_factor: f32,
+    /// How long it takes the driver to act on what happens around it (s).
+    /// The driver reacts to light changes, speed limits and the vehicle in front with this delay.
+    pub reaction_time: f32,
+}
+
+impl Default for DriverBehaviour {
+    fn default() -> Self {
+        Self {
+            desired_speed_factor: 1.0,
+            acceleration_factor: 1.0,
+            time_headway: 1.5,
+            orange_deceleration_factor: 1.0,
+            reaction_time: 0.0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ParameterDistribution {
+    Fixed(f32),
+    Uniform {
+        min: f32,
+        max: f32,
+    },
+    /// A normal distribution that is clamped to the given bounds
+    Normal {
+        mean: f32,
+        std_dev: f32,
+        min: f32,
+        max: f32,
+    },
+}
+
+impl ParameterDistribution {
+    pub fn sample(&self, rng: &mut impl Rng) -> f32 {
+        match *self {
+            ParameterDistribution::Fixed(value) => value,
+            ParameterDistribution::Uniform { min, max } => {
+                if min < max {
+                    rng.gen_range(min..max)
+                } else {
+                    min
+                }
+            }
+            ParameterDistribution::Normal {
+                mean,
+                std_dev,
+                min,
+                max,
+            } => {
+                // Box-Muller transform
+                let u1 = 1.0 - rng.gen::<f32>();
+                let u2 = rng.gen::<f32>();
+                let standard_normal =
+                    (-2.0 * u1.ln()).sqrt() * (2.0 * std::f32::consts::PI * u2).cos();
+
+                (mean + standard_normal * std_dev).clamp(min, max)
+            }
+        }
+    }
+}
+
+/// The distributions from which the behaviour of every new driver is drawn
+#[derive(Debug, Clone, PartialEq)]
+pub struct DriverDistribution {
+    pub desired_speed_factor: ParameterDistribution,
+    pub acceleration_factor: ParameterDistribution,
+    pub time_headway: ParameterDistribution,
+    pub orange_deceleration_factor: ParameterDistribution,
+    pub reaction_time: ParameterDistribution,
+}
+
+impl DriverDistribution {
+    /// Every driver gets the default behaviour
+    pub fn identical() -> Self {
+        let behaviour = DriverBehaviour::default();
+
+        Self {
+            desired_speed_factor: ParameterDistribution::Fixed(behaviour.desired_speed_factor),
+            acceleration_factor: ParameterDistribution::Fixed(behaviour.acceleration_factor),
+            time_headway: ParameterDistribution::Fixed(behaviour.time_headway),
+            orange_deceleration_factor: ParameterDistribution::Fixed(
+                behaviour.orange_deceleration_factor,
+            ),
+            reaction_time: ParameterDistribution::Fixed(behaviour.reaction_time),
+        }
+    }
+
+    pub fn sample(&self, rng: &mut impl Rng) -> DriverBehaviour {
+        DriverBehaviour {
+            desired_speed_factor: self.desired_speed_factor.sample(rng),
+            acceleration_factor: self.acceleration_factor.sample(rng),
+            time_headway: self.time_headway.sample(rng),
+            orange_deceleration_factor: self.orange_deceleration_factor.sample(rng),
+            reaction_time: self.reaction_time.sample(rng),
+        }
+    }
+}
+
+impl Default for DriverDistribution {
+    fn default() -> Self {
+        Self {
+            desired_speed_factor: ParameterDistribution::Normal {
+                mean: 1.0,
+                std_dev: 0.1,
+                min: 0.8,
+                max: 1.2,
+            },
+            acceleration_factor: ParameterDistribution::Normal {
+                mean: 1.0,
+                std_dev: 0.15,
+                min: 0.6,
+                max: 1.4,
+            },
+            time_headway: ParameterDistribution::Normal {
+                mean: 1.5,
+                std_dev: 0.3,
+                min: 0.8,
+                max: 3.0,
+            },
+            orange_deceleration_factor: ParameterDistribution::Uniform { min: 0.5, max: 1.0 },
+            reaction_time: ParameterDistribution::Normal {
+                mean: 0.8,
+                std_dev: 0.2,
+                min: 0.4,
+                max: 1.5,
+            },
+        }
+    }
+}
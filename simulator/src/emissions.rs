@@ -0,0 +1,243 @@
+use std::{
+    collections::HashMap,
+    ops::{AddAssign, Mul},
+};
+
+use crate::vehicle::VehicleClass;
+
+/// Fuel and pollutants. Depending on the context these are rates (per second) or totals.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Emissions {
+    pub fuel: f32, // ml
+    pub co2: f32,  // g
+    pub nox: f32,  // mg
+}
+
+impl AddAssign for Emissions {
+    fn add_assign(&mut self, rhs: Self) {
+        self.fuel += rhs.fuel;
+        self.co2 += rhs.co2;
+        self.nox += rhs.nox;
+    }
+}
+
+impl Mul<f32> for Emissions {
+    type Output = Emissions;
+
+    fn mul(self, rhs: f32) -> Self::Output {
+        Emissions {
+            fuel: self.fuel * rhs,
+            co2: self.co2 * rhs,
+            nox: self.nox * rhs,
+        }
+    }
+}
+
+/// Emission rates looked up by speed and acceleration, like the HBEFA tables
+#[derive(Debug, Clone, PartialEq)]
+pub struct EmissionTable {
+    speeds: Vec<f32>,        // m/s, ascending
+    accelerations: Vec<f32>, // m/s/s, ascending
+    rates: Vec<Emissions>,   // per second, for every speed all accelerations
+}
+
+impl EmissionTable {
+    pub fn new(speeds: Vec<f32>, accelerations: Vec<f32>, rates: Vec<Emissions>) -> Self {
+        assert!(!speeds.is_empty() && !accelerations.is_empty());
+        assert_eq!(speeds.len() * accelerations.len(), rates.len());
+
+        Self {
+            speeds,
+            accelerations,
+            rates,
+        }
+    }
+
+    /// A table derived from a simple road load model of the vehicle class on a flat road.
+    /// It gives plausible figures, but should be replaced by calibrated tables for studies.
+    /// None for classes without a combustion engine.
+    pub fn for_class(class: VehicleClass) -> Option<Self> {
+        let parameters = RoadLoadParameters::for_class(class)?;
+
+        let speeds = (0..=20).map(|i| i as f32 * 2.0).collect::<Vec<_>>();
+        let accelerations = (-6..=6).map(|i| i as f32 * 0.5).collect::<Vec<_>>();
+        let rates = speeds
+            .iter()
+            .flat_map(|speed| {
+                accelerations
+                    .iter()
+                    .map(|acceleration| parameters.emissions(class, *speed, *acceleration))
+            })
+            .collect();
+
+        Some(Self::new(speeds, accelerations, rates))
+    }
+
+    /// Bilinear interpolation in the table. Values outside of it are clamped to the edges.
+    pub fn lookup(&self, speed: f32, acceleration: f32) -> Emissions {
+        let (speed_index, speed_fraction) = interpolation_position(&self.speeds, speed);
+        let (acceleration_index, acceleration_fraction) =
+            interpolation_position(&self.accelerations, acceleration);
+
+        let rate = |speed_index: usize, acceleration_index: usize| {
+            let speed_index = speed_index.min(self.speeds.len() - 1);
+            let acceleration_index = acceleration_index.min(self.accelerations.len() - 1);
+            self.rates[speed_index * self.accelerations.len() + acceleration_index]
+        };
+
+        let mut result = rate(speed_index, acceleration_index)
+            * ((1.0 - speed_fraction) * (1.0 - acceleration_fraction));
+        result += rate(speed_index + 1, acceleration_index)
+            * (speed_fraction * (1.0 - acceleration_fraction));
+        result += rate(speed_index, acceleration_index + 1)
+            * ((1.0 - speed_fraction) * acceleration_fraction);
+        result += rate(speed_index + 1, acceleration_index + 1)
+            * (speed_fraction * acceleration_fraction);
+        result
+    }
+}
+
+/// The index of the bin below the value and how far the value is towards the next one
+fn interpolation_position(bins: &[f32], value: f32) -> (usize, f32) {
+    if value <= bins[0] {
+        return (0, 0.0);
+    }
+
+    match bins.windows(2).position(|bin| value < bin[1]) {
+        Some(index) => (
+            index,
+            (value - bins[index]) / (bins[index + 1] - bins[index]),
+        ),
+        None => (bins.len() - 1, 0.0),
+    }
+}
+
+struct RoadLoadParameters {
+    idle_fuel_rate: f32, // ml/s
+    efficiency: f32,     // engine and drivetrain
+    fuel_energy: f32,    // J/ml
+    co2_per_fuel: f32,   // g/ml
+    nox_per_fuel: f32,   // mg/ml
+}
+
+impl RoadLoadParameters {
+    fn for_class(class: VehicleClass) -> Option<Self> {
+        let parameters = match class {
+            // Petrol
+            VehicleClass::PassengerCar => Self {
+                idle_fuel_rate: 0.25,
+                efficiency: 0.28,
+                fuel_energy: 34_200.0,
+                co2_per_fuel: 2.31,
+                nox_per_fuel: 1.0,
+            },
+            // Diesel
+            VehicleClass::Bus => Self {
+                idle_fuel_rate: 0.8,
+                efficiency: 0.38,
+                fuel_energy: 38_600.0,
+                co2_per_fuel: 2.68,
+                nox_per_fuel: 5.0,
+            },
+            VehicleClass::Truck => Self {
+                idle_fuel_rate: 1.0,
+                efficiency: 0.40,
+                fuel_energy: 38_600.0,
+                co2_per_fuel: 2.68,
+                nox_per_fuel: 4.0,
+            },
+            VehicleClass::ElectricCar => return None,
+        };
+
+        Some(parameters)
+    }
+
+    fn emissions(&self, class: VehicleClass, speed: f32, acceleration: f32) -> Emissions {
+        let power = (class.road_load_force(speed, acceleration, 0.0) * speed).max(0.0);
+
+        let fuel = self.idle_fuel_rate + power / (self.efficiency * self.fuel_energy);
+
+        Emissions {
+            fuel,
+            co2: fuel * self.co2_per_fuel,
+            nox: fuel * self.nox_per_fuel,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct EmissionModel {
+    tables: HashMap<VehicleClass, EmissionTable>,
+}
+
+impl EmissionModel {
+    pub fn set_table(&mut self, class: VehicleClass, table: EmissionTable) {
+        self.tables.insert(class, table);
+    }
+
+    pub fn table(&self, class: VehicleClass) -> Option<&EmissionTable> {
+        self.tables.get(&class)
+    }
+
+    /// The emissions per second. Classes without a table don't emit anything.
+    pub fn rates(&self, class: VehicleClass, speed: f32, acceleration: f32) -> Emissions {
+        self.tables
+            .get(&class)
+            .map(|table| table.lookup(speed, acceleration))
+            .unwrap_or_default()
+    }
+}
+
+impl Default for EmissionModel {
+    fn default() -> Self {
+        Self {
+            tables: [
+                VehicleClass::PassengerCar,
+                VehicleClass::Bus,
+                VehicleClass::Truck,
+            ]
+            .into_iter()
+            .filter_map(|class| Some((class, EmissionTable::for_class(class)?)))
+            .collect(),
+        }
+    }
+}
+
+/// The emissions of a run, aggregated in different ways
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct EmissionsReport {
+    per_road_user: HashMap<u32, Emissions>,
+    per_edge: HashMap<(u32, u32), Emissions>,
+    total: Emissions,
+}
+
+impl EmissionsReport {
+    pub(crate) fn record(&mut self, road_user: u32, edge: Option<(u32, u32)>, emitted: Emissions) {
+        *self.per_road_user.entry(road_user).or_default() += emitted;
+        if let Some(edge) = edge {
+            *self.per_edge.entry(edge).or_default() += emitted;
+        }
+        self.total += emitted;
+    }
+
+    pub fn road_user(&self, id: u32) -> Emissions {
+        self.per_road_user.get(&id).copied().unwrap_or_default()
+    }
+
+    /// The emissions on the edge going from one node to the next
+    pub fn edge(&self, from: u32, to: u32) -> Emissions {
+        self.per_edge.get(&(from, to)).copied().unwrap_or_default()
+    }
+
+    pub fn per_road_user(&self) -> &HashMap<u32, Emissions> {
+        &self.per_road_user
+    }
+
+    pub fn per_edge(&self) -> &HashMap<(u32, u32), Emissions> {
+        &self.per_edge
+    }
+
+    pub fn total(&self) -> Emissions {
+        self.total
+    }
+}
@@ -0,0 +1,32 @@
+use crate::{collision::Collision, scenario::ScheduledAction, transit::StopVisit};
+
+/// Something noteworthy that happened during a simulator tick
+#[derive(Debug, Clone, PartialEq)]
+pub enum SimulationEvent {
+    /// A public transport vehicle finished dwelling at one of its stops
+    TransitStopServed(StopVisit),
+    /// Two road users started overlapping
+    Collision(Collision),
+    /// A road user found a free space at its destination and left the network
+    Parked { road_user: u32, facility: u32 },
+    /// A road user arrived at a full parking facility and has to search for another one
+    ParkingFull { road_user: u32, facility: u32 },
+    /// A road user on a trip chain found no facility with space that it could reach. It
+    /// continues with the next leg of its trip chain without parking.
+    ParkingNotFound { road_user: u32 },
+    /// A parked road user re-entered the network for the next leg of its trip chain
+    LeftParking { road_user: u32, facility: u32 },
+    /// An electric vehicle with a low battery changed its route to a charging station
+    DetouredToCharger { road_user: u32, station: u32 },
+    /// An electric vehicle got a plug at a charging station
+    ChargingStarted { road_user: u32, station: u32 },
+    /// An electric vehicle finished charging and continues to its destination
+    ChargingFinished { road_user: u32, station: u32 },
+    /// An electric vehicle ran out of energy and stopped
+    BatteryDepleted { road_user: u32 },
+    /// A vehicle of the traffic demand couldn't depart because its destination can't be
+    /// reached from its origin. It's left out of the simulation.
+    DemandUnroutable { demand: u32 },
+    /// An action of the scenario timeline was executed
+    ScheduledActionExecuted(ScheduledAction),
+}
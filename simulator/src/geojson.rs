@@ -0,0 +1,114 @@
+use std::io::{self, Write};
+
+use nalgebra::Point3;
+use serde_json::{json, Value};
+
+use crate::{projection::Projection, road::RoadNetwork, user::RoadUser};
+
+/// The nodes as points and the connections to their next nodes as line strings.
+/// Every edge has the speed limit of the node it leads to, as that's what applies on it.
+/// Pass `network.projection()` to use the coordinate reference of the network.
+pub fn network_features(network: &RoadNetwork, projection: &dyn Projection) -> Value {
+    let mut node_ids = network.all_node_ids().collect::<Vec<_>>();
+    node_ids.sort();
+
+    let mut features = Vec::new();
+    for id in node_ids.iter() {
+        let node = network.find_node(*id);
+
+        features.push(feature(
+            json!({
+                "type": "Point",
+                "coordinates": coordinates(projection, node.location()),
+            }),
+            json!({
+                "kind": "node",
+                "id": node.id,
+                "max_speed": node.max_speed(),
+                "adjacent_node_right": node.adjacent_node_right_id(),
+                "adjacent_node_left": node.adjacent_node_left_id(),
+            }),
+        ));
+    }
+
+    for id in node_ids.iter() {
+        let node = network.find_node(*id);
+
+        for next_node in node.next_nodes(network) {
+            features.push(feature(
+                json!({
+                    "type": "LineString",
+                    "coordinates": [
+                        coordinates(projection, node.location()),
+                        coordinates(projection, next_node.location()),
+                    ],
+                }),
+                json!({
+                    "kind": "edge",
+                    "from": node.id,
+                    "to": next_node.id,
+                    "max_speed": next_node.max_speed(),
+                    "length": node.distance_to(next_node),
+                }),
+            ));
+        }
+    }
+
+    feature_collection(features)
+}
+
+/// The road users as points at the given time
+pub fn road_user_features(
+    road_users: &[RoadUser],
+    time: f32,
+    projection: &dyn Projection,
+) -> Value {
+    feature_collection(
+        road_users
+            .iter()
+            .map(|user| {
+                feature(
+                    json!({
+                        "type": "Point",
+                        "coordinates": coordinates(projection, user.location()),
+                    }),
+                    json!({
+                        "kind": "road_user",
+                        "id": user.id,
+                        "time": time,
+                        "class": format!("{:?}", user.class()),
+                        "speed": user.current_speed(),
+                        "acceleration": user.current_acceleration(),
+                        "heading": user.heading(),
+                        "next_node": user.next_node(),
+                    }),
+                )
+            })
+            .collect(),
+    )
+}
+
+pub fn write(features: &Value, writer: impl Write) -> io::Result<()> {
+    serde_json::to_writer_pretty(writer, features).map_err(io::Error::from)
+}
+
+/// GeoJSON positions are `[longitude, latitude, altitude]`
+fn coordinates(projection: &dyn Projection, location: Point3<f32>) -> Value {
+    let point = projection.to_geographic(location);
+    json!([point.longitude, point.latitude, point.altitude])
+}
+
+fn feature(geometry: Value, properties: Value) -> Value {
+    json!({
+        "type": "Feature",
+        "geometry": geometry,
+        "properties": properties,
+    })
+}
+
+fn feature_collection(features: Vec<Value>) -> Value {
+    json!({
+        "type": "FeatureCollection",
+        "features": features,
+    })
+}
@@ -0,0 +1,128 @@
+use std::collections::HashSet;
+
+/// The part of the network an incident is on
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum IncidentLocation {
+    Node(u32),
+    Edge { from: u32, to: u32 },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum IncidentImpact {
+    /// Nothing can pass
+    Closed,
+    /// Traffic can pass at a fraction of the speed limit. On an edge this applies to the node
+    /// the edge leads to.
+    ReducedCapacity { speed_factor: f32 },
+}
+
+/// Something that blocks (a part of) the road for a while, like an accident or roadworks
+#[derive(Debug, Clone, PartialEq)]
+pub struct Incident {
+    id: u32,
+    location: IncidentLocation,
+    impact: IncidentImpact,
+    start: f32,       // s
+    end: Option<f32>, // s, None until it's known when the road reopens
+}
+
+impl Incident {
+    pub fn new(
+        id: u32,
+        location: IncidentLocation,
+        impact: IncidentImpact,
+        start: f32,
+        end: Option<f32>,
+    ) -> Self {
+        Self {
+            id,
+            location,
+            impact,
+            start,
+            end,
+        }
+    }
+
+    pub fn id(&self) -> u32 {
+        self.id
+    }
+
+    pub fn location(&self) -> IncidentLocation {
+        self.location
+    }
+
+    pub fn impact(&self) -> IncidentImpact {
+        self.impact
+    }
+
+    pub fn start(&self) -> f32 {
+        self.start
+    }
+
+    pub fn end(&self) -> Option<f32> {
+        self.end
+    }
+
+    pub fn is_active(&self, time: f32) -> bool {
+        self.start <= time && self.end.is_none_or(|end| time < end)
+    }
+
+    pub(crate) fn set_end(&mut self, end: f32) {
+        self.end = Some(end);
+    }
+}
+
+/// The nodes and edges that can't be driven on right now
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct RoadClosures {
+    nodes: HashSet<u32>,
+    edges: HashSet<(u32, u32)>,
+}
+
+impl RoadClosures {
+    /// The closures of the incidents that are active at the given time
+    pub fn from_incidents<'a>(
+        incidents: impl IntoIterator<Item = &'a Incident>,
+        time: f32,
+    ) -> Self {
+        let mut closures = Self::default();
+
+        for incident in incidents {
+            if incident.impact != IncidentImpact::Closed || !incident.is_active(time) {
+                continue;
+            }
+
+            match incident.location {
+                IncidentLocation::Node(node) => {
+                    closures.nodes.insert(node);
+                }
+                IncidentLocation::Edge { from, to } => {
+                    closures.edges.insert((from, to));
+                }
+            }
+        }
+
+        closures
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty() && self.edges.is_empty()
+    }
+
+    pub fn is_node_closed(&self, node: u32) -> bool {
+        self.nodes.contains(&node)
+    }
+
+    /// Edges into a closed node are closed as well
+    pub fn is_edge_closed(&self, from: u32, to: u32) -> bool {
+        self.edges.contains(&(from, to)) || self.nodes.contains(&to)
+    }
+
+    pub fn closed_nodes(&self) -> impl Iterator<Item = u32> + '_ {
+        self.nodes.iter().copied()
+    }
+
+    pub fn closed_edges(&self) -> impl Iterator<Item = (u32, u32)> + '_ {
+        self.edges.iter().copied()
+    }
+}
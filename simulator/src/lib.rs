@@ -1,16 +1,92 @@
+use std::collections::{HashMap, HashSet};
+
+use battery::EnergyModel;
+use charging::{ChargingStation, ChargingVehicle};
+use collision::CollisionResponse;
+use demand::{ScheduledDeparture, TrafficDemand};
+use detector::Detector;
+use driver::{DriverBehaviour, DriverDistribution};
+use emissions::{EmissionModel, EmissionsReport};
+use event::SimulationEvent;
+use incident::{Incident, IncidentImpact, IncidentLocation, RoadClosures};
+use parking::{ParkedVehicle, ParkingFacility};
+use rand::SeedableRng;
+use rand_chacha::ChaCha8Rng;
 use road::RoadNetwork;
-use traffic_light::TrafficLight;
-use user::RoadUser;
+use scenario::{ScheduledAction, ScheduledEvent};
+use speed_limit::SpeedLimitController;
+use traffic_light::{TimedTrafficLight, TrafficLight};
+use trajectory::Trajectories;
+use transit::{TransitLine, TransitReport};
+use user::{RoadUser, TickContext, TickOutcome};
+use vehicle::VehicleClass;
+use weather::Weather;
 
+pub mod battery;
+pub mod charging;
+pub mod collision;
+pub mod demand;
+pub mod detector;
+pub mod dot;
+pub mod driver;
+pub mod emissions;
+pub mod event;
+pub mod geojson;
+pub mod incident;
+pub mod parking;
+mod perception;
+pub mod projection;
+pub mod ramp_metering;
+pub mod render;
 pub mod road;
+pub mod scenario;
+pub mod speed_limit;
+pub mod sumo;
+pub mod svg;
 pub mod traffic_light;
+pub mod trajectory;
+pub mod transit;
 pub mod user;
+pub mod vehicle;
+pub mod weather;
+
+/// Times closer together than this are the same, which leaves some slack for the rounding
+/// errors of adding up time steps
+pub const TIME_EPSILON: f32 = 1e-4; // s
 
+#[derive(Clone)]
 pub struct Simulator {
     current_time: f32,
     road_network: RoadNetwork,
     current_road_users: Vec<RoadUser>,
+    next_road_user_id: u32,
     traffic_lights: Vec<Box<dyn TrafficLight>>,
+    detectors: Vec<Detector>,
+    speed_limit_controllers: Vec<Box<dyn SpeedLimitController>>,
+    speed_limits: HashMap<u32, f32>,
+    incidents: Vec<Incident>,
+    closures: RoadClosures,
+    weather: Weather,
+    parking_facilities: Vec<ParkingFacility>,
+    parked_vehicles: Vec<ParkedVehicle>,
+    transit_lines: Vec<TransitLine>,
+    transit_report: TransitReport,
+    demand: Vec<TrafficDemand>,
+    /// Sorted by time
+    scheduled_departures: Vec<ScheduledDeparture>,
+    /// Sorted by time, executed events are removed
+    timeline: Vec<ScheduledEvent>,
+    emission_model: EmissionModel,
+    emissions: EmissionsReport,
+    trajectories: Option<Trajectories>,
+    energy_model: EnergyModel,
+    charging_stations: Vec<ChargingStation>,
+    charging_vehicles: Vec<ChargingVehicle>,
+    collision_response: CollisionResponse,
+    ongoing_collisions: HashSet<(u32, u32)>,
+    rng: ChaCha8Rng,
+    driver_distribution: DriverDistribution,
+    events: Vec<SimulationEvent>,
 }
 
 impl Simulator {
@@ -19,21 +95,604 @@ impl Simulator {
             current_time: 0.0,
             road_network,
             current_road_users: Vec::new(),
+            next_road_user_id: 0,
             traffic_lights,
+            detectors: Vec::new(),
+            speed_limit_controllers: Vec::new(),
+            speed_limits: HashMap::new(),
+            incidents: Vec::new(),
+            closures: RoadClosures::default(),
+            weather: Weather::default(),
+            parking_facilities: Vec::new(),
+            parked_vehicles: Vec::new(),
+            transit_lines: Vec::new(),
+            transit_report: TransitReport::default(),
+            demand: Vec::new(),
+            scheduled_departures: Vec::new(),
+            timeline: Vec::new(),
+            emission_model: EmissionModel::default(),
+            emissions: EmissionsReport::default(),
+            trajectories: None,
+            energy_model: EnergyModel::default(),
+            charging_stations: Vec::new(),
+            charging_vehicles: Vec::new(),
+            collision_response: CollisionResponse::default(),
+            ongoing_collisions: HashSet::new(),
+            rng: ChaCha8Rng::seed_from_u64(0),
+            driver_distribution: DriverDistribution::default(),
+            events: Vec::new(),
         }
     }
 
     pub fn tick(&mut self, delta_time: f32) {
-        self.traffic_lights
+        self.events.clear();
+
+        if let Some(trajectories) = self.trajectories.as_mut() {
+            trajectories.record(self.current_time, &self.current_road_users);
+        }
+        self.run_timeline();
+
+        self.detectors.iter_mut().for_each(|detector| {
+            detector.tick(
+                self.current_time,
+                delta_time,
+                &self.road_network,
+                &self.current_road_users,
+            )
+        });
+
+        self.traffic_lights.iter_mut().for_each(|light| {
+            light.observe_detectors(&self.detectors);
+            light.tick(self.current_time)
+        });
+        self.update_closures();
+        self.update_speed_limits();
+
+        self.spawn_transit_vehicles(delta_time);
+        self.spawn_demand(delta_time);
+        self.spawn_scheduled_departures(delta_time);
+        self.release_parked_vehicles();
+        self.charge_vehicles(delta_time);
+
+        let road_users = self
+            .current_road_users
+            .iter()
+            .map(|user| user.snapshot(&self.road_network))
+            .collect::<Vec<_>>();
+        let context = TickContext {
+            network: &self.road_network,
+            traffic_lights: &self.traffic_lights,
+            road_users: &road_users,
+            speed_limits: &self.speed_limits,
+            closures: &self.closures,
+            weather: &self.weather,
+            current_time: self.current_time,
+            delta_time,
+        };
+
+        let outcomes = self
+            .current_road_users
             .iter_mut()
-            .for_each(|light| light.tick(self.current_time));
+            .map(|user| user.tick(&context, &mut self.events))
+            .collect::<Vec<_>>();
 
-        self.current_road_users
-            .retain_mut(|user| user.tick(&self.road_network, &self.traffic_lights, delta_time));
+        for user in self.current_road_users.iter() {
+            if user.battery().is_some() {
+                continue;
+            }
+
+            let rates = self.emission_model.rates(
+                user.class(),
+                user.current_speed(),
+                user.current_acceleration(),
+            );
+            self.emissions
+                .record(user.id, user.current_edge(), rates * delta_time);
+        }
+
+        self.use_battery_energy(&outcomes, delta_time);
+
+        let (driving, finished): (Vec<_>, Vec<_>) = std::mem::take(&mut self.current_road_users)
+            .into_iter()
+            .zip(outcomes)
+            .partition(|(_, outcome)| *outcome == TickOutcome::Driving);
+        self.current_road_users = driving.into_iter().map(|(user, _)| user).collect();
+
+        for (user, outcome) in finished {
+            if outcome == TickOutcome::ReachedDestination {
+                self.arrive(user);
+            }
+        }
+
+        for event in self.events.iter() {
+            if let SimulationEvent::TransitStopServed(visit) = event {
+                self.transit_report.record(visit.clone());
+            }
+        }
+
+        self.detect_collisions();
 
         self.current_time += delta_time;
     }
 
+    /// Parks the road user if it's on a trip chain. Without space at its destination it
+    /// searches for another facility, or continues with the next leg without parking.
+    fn arrive(&mut self, mut user: RoadUser) {
+        if user.is_on_charging_detour() {
+            self.arrive_at_charger(user);
+            return;
+        }
+
+        let destination = user.destination_node();
+        let Some(parking_duration) = user.current_trip_leg().map(|leg| leg.parking_duration) else {
+            return;
+        };
+
+        match self
+            .parking_facilities
+            .iter_mut()
+            .find(|facility| facility.node() == destination)
+        {
+            Some(facility) if !facility.is_full() => {
+                facility.enter();
+                self.events.push(SimulationEvent::Parked {
+                    road_user: user.id,
+                    facility: facility.id(),
+                });
+                self.parked_vehicles.push(ParkedVehicle {
+                    user,
+                    facility: facility.id(),
+                    leaves_at: self.current_time + parking_duration,
+                });
+                return;
+            }
+            Some(facility) => self.events.push(SimulationEvent::ParkingFull {
+                road_user: user.id,
+                facility: facility.id(),
+            }),
+            None => {}
+        }
+
+        if self.search_parking(&mut user) {
+            self.current_road_users.push(user);
+            return;
+        }
+
+        self.events
+            .push(SimulationEvent::ParkingNotFound { road_user: user.id });
+        if user.depart_for_next_leg(destination, &self.road_network, &self.closures) {
+            self.current_road_users.push(user);
+        }
+    }
+
+    /// Sends the road user to the closest facility with space that it can reach.
+    /// Returns false if there's none.
+    fn search_parking(&self, user: &mut RoadUser) -> bool {
+        let location = self
+            .road_network
+            .find_node(user.destination_node())
+            .location();
+        let mut facilities = self
+            .parking_facilities
+            .iter()
+            .filter(|facility| !facility.is_full())
+            .collect::<Vec<_>>();
+        facilities.sort_by(|a, b| {
+            let distance_a =
+                (self.road_network.find_node(a.node()).location() - location).magnitude();
+            let distance_b =
+                (self.road_network.find_node(b.node()).location() - location).magnitude();
+            distance_a.total_cmp(&distance_b)
+        });
+
+        facilities
+            .into_iter()
+            .any(|facility| user.redirect(facility.node(), &self.road_network, &self.closures))
+    }
+
+    fn release_parked_vehicles(&mut self) {
+        let (leaving, parked): (Vec<_>, Vec<_>) = std::mem::take(&mut self.parked_vehicles)
+            .into_iter()
+            .partition(|parked| parked.leaves_at <= self.current_time);
+        self.parked_vehicles = parked;
+
+        for parked in leaving {
+            let Some(parking_facility) = self
+                .parking_facilities
+                .iter_mut()
+                .find(|parking_facility| parking_facility.id() == parked.facility)
+            else {
+                continue;
+            };
+
+            // Stay in the facility until there's room to enter the road
+            let location = self
+                .road_network
+                .find_node(parking_facility.node())
+                .location();
+            if !is_clear(&self.current_road_users, location, parked.user.length()) {
+                self.parked_vehicles.push(parked);
+                continue;
+            }
+            parking_facility.leave();
+
+            let ParkedVehicle {
+                mut user, facility, ..
+            } = parked;
+            if user.depart_for_next_leg(parking_facility.node(), &self.road_network, &self.closures)
+            {
+                self.events.push(SimulationEvent::LeftParking {
+                    road_user: user.id,
+                    facility,
+                });
+                self.current_road_users.push(user);
+            }
+        }
+    }
+
+    /// Only road users that are still driving look for a charger. The others already arrived.
+    fn use_battery_energy(&mut self, outcomes: &[TickOutcome], delta_time: f32) {
+        for (user, outcome) in self.current_road_users.iter_mut().zip(outcomes) {
+            let energy = self.energy_model.energy(
+                user.class(),
+                user.current_speed(),
+                user.current_acceleration(),
+                user.current_direction().z,
+                delta_time,
+            );
+            let Some(battery) = user.battery_mut() else {
+                continue;
+            };
+            battery.discharge(energy);
+
+            if battery.is_depleted() && !user.is_halted() {
+                user.halt();
+                self.events
+                    .push(SimulationEvent::BatteryDepleted { road_user: user.id });
+                continue;
+            }
+
+            if *outcome != TickOutcome::Driving || !user.needs_charging() {
+                continue;
+            }
+
+            // Try the closest charging stations first
+            let location = user.location();
+            let mut stations = self.charging_stations.iter().collect::<Vec<_>>();
+            stations.sort_by(|a, b| {
+                let distance_a =
+                    (self.road_network.find_node(a.node()).location() - location).magnitude();
+                let distance_b =
+                    (self.road_network.find_node(b.node()).location() - location).magnitude();
+                distance_a.total_cmp(&distance_b)
+            });
+
+            match stations.into_iter().find(|station| {
+                user.detour_to_charger(station.node(), &self.road_network, &self.closures)
+            }) {
+                Some(station) => self.events.push(SimulationEvent::DetouredToCharger {
+                    road_user: user.id,
+                    station: station.id(),
+                }),
+                None => user.give_up_charger_search(),
+            }
+        }
+    }
+
+    /// Queues the road user for a plug at the charging station at its destination
+    fn arrive_at_charger(&mut self, mut user: RoadUser) {
+        let node = user.destination_node();
+        match self
+            .charging_stations
+            .iter()
+            .find(|station| station.node() == node)
+        {
+            Some(station) => self.charging_vehicles.push(ChargingVehicle {
+                user,
+                station: station.id(),
+                plugged_in: false,
+            }),
+            None => {
+                if user.depart_after_charging(node, &self.road_network, &self.closures) {
+                    self.current_road_users.push(user);
+                }
+            }
+        }
+    }
+
+    fn charge_vehicles(&mut self, delta_time: f32) {
+        let mut still_charging = Vec::new();
+
+        for mut charging in std::mem::take(&mut self.charging_vehicles) {
+            let Some(station) = self
+                .charging_stations
+                .iter_mut()
+                .find(|station| station.id() == charging.station)
+            else {
+                continue;
+            };
+
+            if !charging.plugged_in {
+                if station.free_plugs() == 0 {
+                    still_charging.push(charging);
+                    continue;
+                }
+
+                station.plug_in();
+                charging.plugged_in = true;
+                self.events.push(SimulationEvent::ChargingStarted {
+                    road_user: charging.user.id,
+                    station: station.id(),
+                });
+            }
+
+            let Some(battery) = charging.user.battery_mut() else {
+                continue;
+            };
+            battery.discharge(-station.power() * delta_time / 3600.0);
+
+            if !battery.is_charged() {
+                still_charging.push(charging);
+                continue;
+            }
+
+            station.unplug();
+            self.events.push(SimulationEvent::ChargingFinished {
+                road_user: charging.user.id,
+                station: station.id(),
+            });
+
+            let mut user = charging.user;
+            if user.depart_after_charging(station.node(), &self.road_network, &self.closures) {
+                self.current_road_users.push(user);
+            }
+        }
+
+        self.charging_vehicles = still_charging;
+    }
+
+    fn detect_collisions(&mut self) {
+        let overlapping = collision::find_overlapping(&self.current_road_users);
+
+        let mut current_collisions = HashSet::new();
+        let mut involved = HashSet::new();
+
+        for (a, b) in overlapping {
+            let (user_a, user_b) = (&self.current_road_users[a], &self.current_road_users[b]);
+            let pair = (user_a.id.min(user_b.id), user_a.id.max(user_b.id));
+
+            if !self.ongoing_collisions.contains(&pair) {
+                self.events
+                    .push(SimulationEvent::Collision(collision::collision_between(
+                        self.current_time,
+                        user_a,
+                        user_b,
+                    )));
+                involved.extend([a, b]);
+            }
+
+            current_collisions.insert(pair);
+        }
+
+        self.ongoing_collisions = current_collisions;
+
+        match self.collision_response {
+            CollisionResponse::Report => {}
+            CollisionResponse::Halt => involved
+                .iter()
+                .for_each(|index| self.current_road_users[*index].halt()),
+            CollisionResponse::Remove => {
+                let mut index = 0;
+                self.current_road_users.retain(|_| {
+                    index += 1;
+                    !involved.contains(&(index - 1))
+                });
+            }
+        }
+    }
+
+    /// Road users pick a new path when parts of the network close or reopen
+    fn update_closures(&mut self) {
+        let closures = RoadClosures::from_incidents(&self.incidents, self.current_time);
+        if closures == self.closures {
+            return;
+        }
+
+        self.closures = closures;
+        for user in self.current_road_users.iter_mut() {
+            user.avoid_closures(&self.road_network, &self.closures);
+            user.search_charger_again();
+        }
+    }
+
+    fn update_speed_limits(&mut self) {
+        self.speed_limits.clear();
+
+        for controller in self.speed_limit_controllers.iter_mut() {
+            controller.tick(self.current_time, &self.detectors);
+
+            let Some(limit) = controller.current_limit() else {
+                continue;
+            };
+
+            for node in controller.nodes() {
+                self.speed_limits
+                    .entry(*node)
+                    .and_modify(|current| *current = current.min(limit))
+                    .or_insert(limit);
+            }
+        }
+
+        // Several incidents at the same node don't add up, the strongest reduction counts
+        let mut speed_factors = HashMap::new();
+        for incident in self.incidents.iter() {
+            let IncidentImpact::ReducedCapacity { speed_factor } = incident.impact() else {
+                continue;
+            };
+            if !incident.is_active(self.current_time) {
+                continue;
+            }
+
+            let node = match incident.location() {
+                IncidentLocation::Node(node) => node,
+                IncidentLocation::Edge { to, .. } => to,
+            };
+            speed_factors
+                .entry(node)
+                .and_modify(|current: &mut f32| *current = current.min(speed_factor))
+                .or_insert(speed_factor);
+        }
+
+        for (node, speed_factor) in speed_factors {
+            let limit = self.current_speed_limit(node) * speed_factor;
+            self.speed_limits.insert(node, limit);
+        }
+    }
+
+    fn spawn_transit_vehicles(&mut self, delta_time: f32) {
+        for line in self.transit_lines.iter() {
+            for departure_time in line
+                .departures()
+                .between(self.current_time, self.current_time + delta_time)
+            {
+                let driver_behaviour = self.driver_distribution.sample(&mut self.rng);
+                self.current_road_users.push(
+                    line.spawn_vehicle(self.next_road_user_id, departure_time, &self.road_network)
+                        .with_driver_behaviour(driver_behaviour),
+                );
+                self.next_road_user_id += 1;
+            }
+        }
+    }
+
+    /// Lets at most one waiting vehicle of every demand enter, if its origin is clear
+    fn spawn_demand(&mut self, delta_time: f32) {
+        for demand in self.demand.iter_mut() {
+            demand.queue_departures(
+                self.current_time,
+                self.current_time + delta_time,
+                &mut self.rng,
+            );
+            if demand.waiting_vehicles() == 0 {
+                continue;
+            }
+
+            let origin = self.road_network.find_node(demand.origin()).location();
+            if !is_clear(
+                &self.current_road_users,
+                origin,
+                demand.vehicle_class().default_length(),
+            ) {
+                continue;
+            }
+
+            demand.depart();
+            let Some(user) = RoadUser::departing_from(
+                self.next_road_user_id,
+                demand.vehicle_class(),
+                demand.origin(),
+                demand.destination(),
+                &self.road_network,
+                &self.closures,
+            ) else {
+                self.events.push(SimulationEvent::DemandUnroutable {
+                    demand: demand.id(),
+                });
+                continue;
+            };
+
+            let driver_behaviour = self.driver_distribution.sample(&mut self.rng);
+            self.current_road_users
+                .push(user.with_driver_behaviour(driver_behaviour));
+            self.next_road_user_id += 1;
+        }
+    }
+
+    /// Departures whose origin is blocked wait until it's clear
+    fn spawn_scheduled_departures(&mut self, delta_time: f32) {
+        let mut index = 0;
+        while let Some(departure) = self.scheduled_departures.get(index) {
+            if departure.time >= self.current_time + delta_time {
+                break;
+            }
+
+            if !is_clear(
+                &self.current_road_users,
+                departure.user.location(),
+                departure.user.length(),
+            ) {
+                index += 1;
+                continue;
+            }
+
+            let mut user = self.scheduled_departures.remove(index).user;
+            user.id = self.next_road_user_id;
+            self.add_manual_road_users(user);
+        }
+    }
+
+    /// Executes the scheduled events whose time has come
+    fn run_timeline(&mut self) {
+        let due = self
+            .timeline
+            .iter()
+            .take_while(|event| event.time <= self.current_time)
+            .count();
+
+        for event in self.timeline.drain(..due).collect::<Vec<_>>() {
+            self.execute(&event);
+            self.events
+                .push(SimulationEvent::ScheduledActionExecuted(event.action));
+        }
+    }
+
+    fn execute(&mut self, event: &ScheduledEvent) {
+        match &event.action {
+            ScheduledAction::CloseNode { node, duration } => {
+                let id = self
+                    .incidents
+                    .iter()
+                    .map(|incident| incident.id() + 1)
+                    .max()
+                    .unwrap_or_default();
+                self.add_incident(Incident::new(
+                    id,
+                    IncidentLocation::Node(*node),
+                    IncidentImpact::Closed,
+                    event.time,
+                    duration.map(|duration| event.time + duration),
+                ));
+            }
+            ScheduledAction::ReopenNode { node } => {
+                for incident in self.incidents.iter_mut() {
+                    if incident.location() == IncidentLocation::Node(*node)
+                        && incident.is_active(self.current_time)
+                    {
+                        incident.set_end(self.current_time);
+                    }
+                }
+            }
+            ScheduledAction::SetTrafficLightSchema { node, schema } => {
+                self.set_traffic_light(Box::new(TimedTrafficLight::new(*node, schema.clone())));
+            }
+            ScheduledAction::SetSpeedLimit { nodes, speed_limit } => {
+                for node in nodes {
+                    self.road_network
+                        .find_node_mut(*node)
+                        .set_max_speed(*speed_limit);
+                }
+            }
+            ScheduledAction::InjectVehicles { vehicles } => {
+                for vehicle in vehicles {
+                    self.inject_vehicle(vehicle.origin, vehicle.destination, vehicle.class);
+                }
+            }
+            ScheduledAction::SetDemandRate { demand, rate } => {
+                self.set_demand_rate(*demand, *rate);
+            }
+        }
+    }
+
     pub fn road_network(&self) -> &RoadNetwork {
         &self.road_network
     }
@@ -42,36 +701,393 @@ impl Simulator {
         self.current_road_users.as_ref()
     }
 
-    pub fn add_manual_road_users(&mut self, user: RoadUser) {
+    /// Road users without a driver behaviour get one drawn from the driver distribution
+    pub fn add_manual_road_users(&mut self, mut user: RoadUser) {
+        if !user.has_driver_behaviour() {
+            user = user.with_driver_behaviour(self.sample_driver_behaviour());
+        }
+
+        self.next_road_user_id = self.next_road_user_id.max(user.id + 1);
         self.current_road_users.push(user)
     }
 
+    /// Adds a road user of the class that departs standing still from the origin.
+    /// Returns its id, or None if the destination can't be reached.
+    pub fn inject_vehicle(
+        &mut self,
+        origin: u32,
+        destination: u32,
+        class: VehicleClass,
+    ) -> Option<u32> {
+        let user = RoadUser::departing_from(
+            self.next_road_user_id,
+            class,
+            origin,
+            destination,
+            &self.road_network,
+            &self.closures,
+        )?;
+
+        let id = user.id;
+        self.add_manual_road_users(user);
+        Some(id)
+    }
+
+    /// Restarts the random number generator. Runs with the same seed and inputs are identical.
+    pub fn set_seed(&mut self, seed: u64) {
+        self.rng = ChaCha8Rng::seed_from_u64(seed);
+    }
+
+    pub fn driver_distribution(&self) -> &DriverDistribution {
+        &self.driver_distribution
+    }
+
+    pub fn set_driver_distribution(&mut self, driver_distribution: DriverDistribution) {
+        self.driver_distribution = driver_distribution;
+    }
+
+    pub fn sample_driver_behaviour(&mut self) -> DriverBehaviour {
+        self.driver_distribution.sample(&mut self.rng)
+    }
+
+    pub fn add_demand(&mut self, demand: TrafficDemand) {
+        self.demand.push(demand)
+    }
+
+    pub fn demand(&self) -> &[TrafficDemand] {
+        self.demand.as_ref()
+    }
+
+    /// Returns false if there's no demand with the id
+    pub fn set_demand_rate(&mut self, id: u32, rate: f32) -> bool {
+        let Some(demand) = self.demand.iter_mut().find(|demand| demand.id() == id) else {
+            return false;
+        };
+
+        demand.set_rate(rate);
+        true
+    }
+
+    /// Lets the road user enter the network at the given time. It gets a new id when it does.
+    pub fn schedule_departure(&mut self, time: f32, user: RoadUser) {
+        let index = self
+            .scheduled_departures
+            .partition_point(|departure| departure.time <= time);
+        self.scheduled_departures
+            .insert(index, ScheduledDeparture { time, user });
+    }
+
+    /// The road users that haven't entered the network yet with their departure times
+    pub fn pending_departures(&self) -> impl Iterator<Item = (f32, &RoadUser)> + '_ {
+        self.scheduled_departures
+            .iter()
+            .map(|departure| (departure.time, &departure.user))
+    }
+
+    /// Events in the past are executed at the start of the next tick
+    pub fn schedule_event(&mut self, event: ScheduledEvent) {
+        let index = self
+            .timeline
+            .partition_point(|scheduled| scheduled.time <= event.time);
+        self.timeline.insert(index, event);
+    }
+
+    /// The events that haven't been executed yet, in order
+    pub fn scheduled_events(&self) -> &[ScheduledEvent] {
+        self.timeline.as_ref()
+    }
+
+    pub fn add_detector(&mut self, detector: Detector) {
+        self.detectors.push(detector)
+    }
+
+    pub fn detectors(&self) -> &[Detector] {
+        self.detectors.as_ref()
+    }
+
+    pub fn add_speed_limit_controller(&mut self, controller: Box<dyn SpeedLimitController>) {
+        self.speed_limit_controllers.push(controller)
+    }
+
+    pub fn speed_limit_controllers(&self) -> &[Box<dyn SpeedLimitController>] {
+        self.speed_limit_controllers.as_ref()
+    }
+
+    /// The speed limit that currently applies on the node, taking the controllers into account
+    pub fn current_speed_limit(&self, node: u32) -> f32 {
+        self.speed_limits
+            .get(&node)
+            .copied()
+            .unwrap_or_else(|| self.road_network.find_node(node).max_speed())
+    }
+
+    pub fn add_incident(&mut self, incident: Incident) {
+        self.incidents.push(incident)
+    }
+
+    pub fn incidents(&self) -> &[Incident] {
+        self.incidents.as_ref()
+    }
+
+    /// Reopens the road at the current time. Returns false if there's no incident with the id.
+    pub fn end_incident(&mut self, id: u32) -> bool {
+        let Some(incident) = self
+            .incidents
+            .iter_mut()
+            .find(|incident| incident.id() == id)
+        else {
+            return false;
+        };
+
+        incident.set_end(self.current_time);
+        true
+    }
+
+    /// The nodes and edges that are closed right now
+    pub fn closures(&self) -> &RoadClosures {
+        &self.closures
+    }
+
+    pub fn emission_model(&self) -> &EmissionModel {
+        &self.emission_model
+    }
+
+    pub fn set_emission_model(&mut self, emission_model: EmissionModel) {
+        self.emission_model = emission_model;
+    }
+
+    pub fn emissions(&self) -> &EmissionsReport {
+        &self.emissions
+    }
+
+    pub fn energy_model(&self) -> &EnergyModel {
+        &self.energy_model
+    }
+
+    pub fn set_energy_model(&mut self, energy_model: EnergyModel) {
+        self.energy_model = energy_model;
+    }
+
+    pub fn add_charging_station(&mut self, station: ChargingStation) {
+        self.charging_stations.push(station);
+        self.current_road_users
+            .iter_mut()
+            .for_each(RoadUser::search_charger_again);
+    }
+
+    pub fn charging_stations(&self) -> &[ChargingStation] {
+        self.charging_stations.as_ref()
+    }
+
+    /// The electric vehicles that are at a charging station, waiting or charging
+    pub fn charging_road_users(&self) -> impl Iterator<Item = &RoadUser> + '_ {
+        self.charging_vehicles.iter().map(|charging| &charging.user)
+    }
+
+    /// Starts recording the trajectories of all road users every `interval` seconds
+    pub fn record_trajectories(&mut self, interval: f32) {
+        self.trajectories = Some(Trajectories::new(interval));
+    }
+
+    /// None if they're not being recorded
+    pub fn trajectories(&self) -> Option<&Trajectories> {
+        self.trajectories.as_ref()
+    }
+
+    pub fn weather(&self) -> &Weather {
+        &self.weather
+    }
+
+    pub fn set_weather(&mut self, weather: Weather) {
+        self.weather = weather;
+    }
+
+    pub fn add_parking_facility(&mut self, facility: ParkingFacility) {
+        self.parking_facilities.push(facility)
+    }
+
+    pub fn parking_facilities(&self) -> &[ParkingFacility] {
+        self.parking_facilities.as_ref()
+    }
+
+    /// The road users that are currently parked and out of the network
+    pub fn parked_road_users(&self) -> impl Iterator<Item = &RoadUser> + '_ {
+        self.parked_vehicles.iter().map(|parked| &parked.user)
+    }
+
+    pub fn add_transit_line(&mut self, line: TransitLine) {
+        self.transit_lines.push(line)
+    }
+
+    pub fn transit_lines(&self) -> &[TransitLine] {
+        self.transit_lines.as_ref()
+    }
+
+    pub fn transit_report(&self) -> &TransitReport {
+        &self.transit_report
+    }
+
+    pub fn collision_response(&self) -> CollisionResponse {
+        self.collision_response
+    }
+
+    pub fn set_collision_response(&mut self, collision_response: CollisionResponse) {
+        self.collision_response = collision_response;
+    }
+
+    /// The events that happened during the last tick
+    pub fn events(&self) -> &[SimulationEvent] {
+        self.events.as_ref()
+    }
+
     pub fn current_time(&self) -> f32 {
         self.current_time
     }
 
+    /// The state needed to continue the simulation from now. It leaves out the transit stop
+    /// visits, the trajectory points and the events, so it doesn't grow with the simulated time.
+    pub fn snapshot(&self) -> Snapshot {
+        Snapshot(Self {
+            current_time: self.current_time,
+            road_network: self.road_network.clone(),
+            current_road_users: self.current_road_users.clone(),
+            next_road_user_id: self.next_road_user_id,
+            traffic_lights: self.traffic_lights.clone(),
+            detectors: self.detectors.clone(),
+            speed_limit_controllers: self.speed_limit_controllers.clone(),
+            speed_limits: self.speed_limits.clone(),
+            incidents: self.incidents.clone(),
+            closures: self.closures.clone(),
+            weather: self.weather.clone(),
+            parking_facilities: self.parking_facilities.clone(),
+            parked_vehicles: self.parked_vehicles.clone(),
+            transit_lines: self.transit_lines.clone(),
+            transit_report: TransitReport::default(),
+            demand: self.demand.clone(),
+            scheduled_departures: self.scheduled_departures.clone(),
+            timeline: self.timeline.clone(),
+            emission_model: self.emission_model.clone(),
+            emissions: self.emissions.clone(),
+            trajectories: self.trajectories.as_ref().map(Trajectories::without_points),
+            energy_model: self.energy_model,
+            charging_stations: self.charging_stations.clone(),
+            charging_vehicles: self.charging_vehicles.clone(),
+            collision_response: self.collision_response,
+            ongoing_collisions: self.ongoing_collisions.clone(),
+            rng: self.rng.clone(),
+            driver_distribution: self.driver_distribution.clone(),
+            events: Vec::new(),
+        })
+    }
+
+    /// Goes back to a snapshot that was taken earlier in this simulation. The transit stop
+    /// visits and trajectory points are kept up to the time of the snapshot, the emissions
+    /// are the ones at that time.
+    pub fn restore(&mut self, snapshot: &Snapshot) {
+        let time = snapshot.current_time();
+        let mut transit_report = std::mem::take(&mut self.transit_report);
+        transit_report.truncate(time);
+        let points = self
+            .trajectories
+            .take()
+            .map(|trajectories| trajectories.into_points_before(time))
+            .unwrap_or_default();
+
+        *self = snapshot.0.clone();
+        self.transit_report = transit_report;
+        if let Some(trajectories) = self.trajectories.as_mut() {
+            trajectories.set_points(points);
+        }
+    }
+
     pub fn traffic_lights(&self) -> &[Box<dyn TrafficLight>] {
         self.traffic_lights.as_ref()
     }
+
+    /// Replaces the traffic light at the same node, or adds it if there's none yet
+    pub fn set_traffic_light(&mut self, light: Box<dyn TrafficLight>) {
+        match self
+            .traffic_lights
+            .iter_mut()
+            .find(|existing| existing.node() == light.node())
+        {
+            Some(existing) => *existing = light,
+            None => self.traffic_lights.push(light),
+        }
+    }
+}
+
+/// The state of a simulation at one moment, to go back to with `Simulator::restore`
+#[derive(Clone)]
+pub struct Snapshot(Simulator);
+
+impl Snapshot {
+    pub fn current_time(&self) -> f32 {
+        self.0.current_time
+    }
+}
+
+/// True if a vehicle of the length fits at the location without touching anyone
+fn is_clear(road_users: &[RoadUser], location: nalgebra::Point3<f32>, length: f32) -> bool {
+    road_users
+        .iter()
+        .all(|user| (user.location() - location).magnitude() > (user.length() + length) / 2.0 + 2.0)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
     use crate::{
+        battery::Battery,
+        incident::{IncidentImpact, IncidentLocation},
+        parking::TripLeg,
+        projection::{Equirectangular, GeoPoint, Projection, TransverseMercator},
+        ramp_metering::{AlineaRampMeter, FixedRateRampMeter},
+        render::{FrameRenderer, RenderError},
         road::Node,
+        scenario::Scenario,
+        speed_limit::{OccupancySpeedLimit, ScheduledSpeedLimit},
+        sumo::{SumoError, SumoNetwork, SumoRoutes},
         traffic_light::{TimedTrafficLight, TrafficLightState},
+        transit::{Departures, TransitStop},
+        vehicle::VehicleClass,
+        weather::{WeatherCondition, WeatherSchedule, WeatherZone},
     };
     use nalgebra::Point3;
-    use std::f32::consts::PI;
+    use std::{collections::HashMap, f32::consts::PI};
 
-    #[test]
-    fn single_node_one_car() {
-        let mut simulator = Simulator::new(
-            RoadNetwork::new(
-                [
+    /// Nodes in a straight line along the x axis, each connected to the next one
+    fn straight_road(nodes: u32, spacing: f32) -> RoadNetwork {
+        RoadNetwork::new(
+            (0..nodes)
+                .map(|id| {
                     (
-                        0,
+                        id,
+                        Node::new(
+                            id,
+                            Point3::new(id as f32 * spacing, 0.0, 0.0),
+                            50.0 / 3.6, // 50kph
+                            if id + 1 < nodes {
+                                vec![id + 1]
+                            } else {
+                                Vec::new()
+                            },
+                            None,
+                            None,
+                        ),
+                    )
+                })
+                .collect(),
+        )
+    }
+
+    #[test]
+    fn single_node_one_car() {
+        let mut simulator = Simulator::new(
+            RoadNetwork::new(
+                [
+                    (
+                        0,
                         Node::new(
                             0,
                             Point3::new(0.0, 0.0, 0.0),
@@ -189,4 +1205,1856 @@ mod tests {
             }
         }
     }
+
+    #[test]
+    fn bus_line_serves_its_stops() {
+        let mut simulator = Simulator::new(
+            RoadNetwork::new(
+                (0..4)
+                    .map(|id| {
+                        (
+                            id,
+                            Node::new(
+                                id,
+                                Point3::new(id as f32 * 50.0, 0.0, 0.0),
+                                50.0 / 3.6, // 50kph
+                                if id < 3 { vec![id + 1] } else { Vec::new() },
+                                None,
+                                None,
+                            ),
+                        )
+                    })
+                    .collect(),
+            ),
+            Vec::new(),
+        );
+
+        simulator.add_transit_line(TransitLine::new(
+            0,
+            vec![0, 1, 2, 3],
+            vec![
+                TransitStop::new(1, 10.0, 5.0, false),
+                TransitStop::new(2, 25.0, 5.0, true),
+            ],
+            Departures::Headway {
+                first: 0.0,
+                headway: 60.0,
+                last: 60.0,
+            },
+        ));
+
+        const DELTA_TIME: f32 = 0.05;
+        for _ in 0..4000 {
+            simulator.tick(DELTA_TIME);
+        }
+
+        let report = simulator.transit_report();
+        assert_eq!(report.visits().len(), 4);
+        assert!(simulator.current_road_users().is_empty());
+
+        let adherence = report.schedule_adherence(0, 60.0, 60.0).unwrap();
+        assert_eq!(adherence.visits, 4);
+        assert_eq!(adherence.on_time_fraction, 1.0);
+
+        let bunching = report.bunching(0, 0.5).unwrap();
+        assert_eq!(bunching.headways, 2);
+        assert_eq!(bunching.bunched, 0);
+    }
+
+    #[test]
+    #[should_panic(expected = "A transit headway must be positive")]
+    fn transit_headway_must_be_positive() {
+        TransitLine::new(
+            0,
+            vec![0, 1],
+            Vec::new(),
+            Departures::Headway {
+                first: 0.0,
+                headway: 0.0,
+                last: 60.0,
+            },
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "Transit stops must be on the route, in the order of the route")]
+    fn transit_stops_must_follow_the_route() {
+        TransitLine::new(
+            0,
+            vec![0, 1, 2, 3],
+            vec![
+                TransitStop::new(2, 25.0, 5.0, false),
+                TransitStop::new(1, 10.0, 5.0, false),
+            ],
+            Departures::Timetable(vec![0.0]),
+        );
+    }
+
+    #[test]
+    fn cars_stop_behind_the_vehicle_in_front() {
+        // The leader stands further from the end of the edge than the lookahead
+        let mut simulator = Simulator::new(straight_road(2, 300.0), Vec::new());
+
+        let mut leader = RoadUser::new(
+            0,
+            Point3::new(50.0, 0.0, 0.0),
+            0.0,
+            3.5,
+            5.0,
+            PI / 2.0,
+            1,
+            1,
+            &simulator.road_network,
+        );
+        leader.halt();
+        simulator.add_manual_road_users(leader);
+        simulator.add_manual_road_users(
+            RoadUser::new(
+                1,
+                Point3::new(-1.0, 0.0, 0.0),
+                10.0,
+                3.5,
+                5.0,
+                PI / 2.0,
+                0,
+                1,
+                &simulator.road_network,
+            )
+            .with_driver_behaviour(DriverBehaviour::default()),
+        );
+
+        for _ in 0..300 {
+            simulator.tick(0.1);
+            assert!(simulator.events().is_empty());
+        }
+
+        let follower = &simulator.current_road_users()[1];
+        assert!(follower.current_speed() < 0.1);
+        let gap = 50.0 - follower.location().x - follower.length();
+        assert!((1.0..10.0).contains(&gap), "gap of {gap} m");
+    }
+
+    #[test]
+    fn nodes_before_a_red_light_further_ahead_are_passed() {
+        let mut simulator = Simulator::new(
+            straight_road(5, 20.0),
+            vec![Box::new(TimedTrafficLight::new(
+                3,
+                vec![(10.0, TrafficLightState::Red)],
+            ))],
+        );
+        simulator
+            .inject_vehicle(0, 4, VehicleClass::PassengerCar)
+            .unwrap();
+
+        for _ in 0..300 {
+            simulator.tick(0.1);
+        }
+
+        // Only the node of the light itself holds the car, it waits right in front of it
+        let user = &simulator.current_road_users()[0];
+        assert_eq!(user.next_node(), 3);
+        assert!(user.current_speed() < 0.1);
+        assert!((50.0..60.0).contains(&user.location().x));
+    }
+
+    #[test]
+    fn overlapping_cars_collide() {
+        let mut simulator = Simulator::new(
+            RoadNetwork::new(
+                [
+                    (
+                        0,
+                        Node::new(
+                            0,
+                            Point3::new(0.0, 0.0, 0.0),
+                            50.0 / 3.6,
+                            vec![1],
+                            None,
+                            None,
+                        ),
+                    ),
+                    (
+                        1,
+                        Node::new(
+                            1,
+                            Point3::new(100.0, 0.0, 0.0),
+                            50.0 / 3.6,
+                            Vec::new(),
+                            None,
+                            None,
+                        ),
+                    ),
+                ]
+                .into(),
+            ),
+            Vec::new(),
+        );
+        simulator.set_collision_response(CollisionResponse::Halt);
+
+        for (id, x) in [(0, 10.0), (1, 12.0)] {
+            let user = RoadUser::new(
+                id,
+                Point3::new(x, 0.0, 0.0),
+                0.0,
+                3.5,
+                5.0,
+                PI / 2.0,
+                1,
+                1,
+                &simulator.road_network,
+            );
+            simulator.add_manual_road_users(user);
+        }
+
+        simulator.tick(0.01);
+
+        let collisions = simulator
+            .events()
+            .iter()
+            .filter_map(|event| match event {
+                SimulationEvent::Collision(collision) => Some(collision),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(collisions.len(), 1);
+        assert_eq!(collisions[0].involved.map(|party| party.id), [0, 1]);
+        assert!(simulator
+            .current_road_users()
+            .iter()
+            .all(RoadUser::is_halted));
+
+        simulator.tick(0.01);
+        assert!(simulator.events().is_empty());
+    }
+
+    #[test]
+    fn driver_behaviour_is_reproducible_from_seed() {
+        let sample = |seed| {
+            let mut simulator = Simulator::new(RoadNetwork::new(HashMap::new()), Vec::new());
+            simulator.set_seed(seed);
+            (0..10)
+                .map(|_| simulator.sample_driver_behaviour())
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(sample(42), sample(42));
+        assert_ne!(sample(42), sample(43));
+
+        // Only the acceleration differs between the drivers, so it has to show in the speed
+        let speed_profile = |seed| {
+            let mut simulator = Simulator::new(straight_road(2, 200.0), Vec::new());
+            simulator.set_seed(seed);
+            simulator.set_driver_distribution(DriverDistribution {
+                acceleration_factor: driver::ParameterDistribution::Uniform { min: 0.6, max: 1.4 },
+                ..DriverDistribution::identical()
+            });
+            simulator
+                .inject_vehicle(0, 1, VehicleClass::PassengerCar)
+                .unwrap();
+
+            let acceleration_factor = simulator.current_road_users()[0]
+                .driver_behaviour()
+                .acceleration_factor;
+            let speeds = (0..20)
+                .map(|_| {
+                    simulator.tick(0.1);
+                    simulator.current_road_users()[0].current_speed()
+                })
+                .collect::<Vec<_>>();
+            (acceleration_factor, speeds)
+        };
+
+        let (first_factor, first_speeds) = speed_profile(42);
+        let (second_factor, second_speeds) = speed_profile(43);
+        assert_ne!(first_factor, second_factor);
+        assert_ne!(first_speeds, second_speeds);
+        let ratio = first_speeds[10] / second_speeds[10];
+        assert!((ratio - first_factor / second_factor).abs() < 0.01);
+    }
+
+    #[test]
+    fn drivers_pull_away_after_their_reaction_time() {
+        let mut simulator = Simulator::new(
+            straight_road(3, 40.0),
+            vec![Box::new(TimedTrafficLight::new(
+                1,
+                vec![
+                    (10.0, TrafficLightState::Red),
+                    (100.0, TrafficLightState::Green),
+                ],
+            ))],
+        );
+        simulator.add_manual_road_users(
+            RoadUser::new(
+                0,
+                Point3::new(-1.0, 0.0, 0.0),
+                0.0,
+                3.5,
+                5.0,
+                PI / 2.0,
+                0,
+                2,
+                &simulator.road_network,
+            )
+            .with_driver_behaviour(DriverBehaviour {
+                reaction_time: 1.0,
+                ..Default::default()
+            }),
+        );
+
+        while simulator.current_time() < 10.0 {
+            simulator.tick(0.05);
+        }
+        assert!(simulator.current_road_users()[0].current_speed() < 0.01);
+
+        // The light turns green right after 10 seconds
+        while simulator.current_road_users()[0].current_speed() < 0.01 {
+            simulator.tick(0.05);
+        }
+        assert!((simulator.current_time() - 11.0).abs() < 0.15);
+    }
+
+    #[test]
+    fn drivers_brake_for_their_leader_after_their_reaction_time() {
+        let braking_delay = |reaction_time| {
+            let mut simulator = Simulator::new(straight_road(2, 300.0), Vec::new());
+            for (id, x) in [(0, 100.0), (1, 67.5)] {
+                simulator.add_manual_road_users(
+                    RoadUser::new(
+                        id,
+                        Point3::new(x, 0.0, 0.0),
+                        50.0 / 3.6,
+                        3.5,
+                        5.0,
+                        PI / 2.0,
+                        1,
+                        1,
+                        &simulator.road_network,
+                    )
+                    .with_driver_behaviour(DriverBehaviour {
+                        reaction_time,
+                        ..Default::default()
+                    }),
+                );
+            }
+
+            for _ in 0..20 {
+                simulator.tick(0.05);
+            }
+            assert!(simulator.current_road_users[1].current_speed() > 13.8);
+
+            simulator.current_road_users[0].halt();
+            let halted_at = simulator.current_time();
+            while simulator.current_road_users[1].current_speed() > 13.8 {
+                simulator.tick(0.05);
+            }
+            simulator.current_time() - halted_at
+        };
+
+        assert!(braking_delay(0.0) < 0.15);
+        assert!((braking_delay(0.5) - 0.55).abs() < 0.06);
+    }
+
+    #[test]
+    fn scheduled_speed_limits_switch_on_time() {
+        let mut controller = ScheduledSpeedLimit::new(
+            vec![1],
+            vec![(10.0, 20.0, 5.0), (30.0, 40.0, 8.0)],
+            Some(60.0),
+        );
+
+        for (time, limit) in [
+            (0.0, None),
+            (10.0, Some(5.0)),
+            (19.9, Some(5.0)),
+            (20.0, None),
+            (35.0, Some(8.0)),
+            (40.0, None),
+            (75.0, Some(5.0)),
+        ] {
+            controller.tick(time, &[]);
+            assert_eq!(controller.current_limit(), limit, "at {time} s");
+        }
+
+        let mut simulator = Simulator::new(straight_road(3, 100.0), Vec::new());
+        simulator.add_speed_limit_controller(Box::new(controller));
+        while simulator.current_time() < 12.0 {
+            simulator.tick(0.1);
+        }
+        assert_eq!(simulator.current_speed_limit(1), 5.0);
+        assert_eq!(simulator.current_speed_limit(2), 50.0 / 3.6);
+    }
+
+    #[test]
+    fn occupancy_lowers_the_speed_limit_and_restores_it() {
+        let mut simulator = Simulator::new(straight_road(3, 100.0), Vec::new());
+        simulator.add_detector(Detector::new(0, 1, 10.0));
+        simulator.add_speed_limit_controller(Box::new(OccupancySpeedLimit::new(
+            vec![0, 1],
+            0,
+            vec![(0.5, 6.0), (0.2, 10.0)],
+            5.0,
+        )));
+
+        // A broken down car stands on the detector for 3 seconds
+        let mut user = RoadUser::new(
+            0,
+            Point3::new(100.0, 0.0, 0.0),
+            0.0,
+            3.5,
+            5.0,
+            PI / 2.0,
+            2,
+            2,
+            &simulator.road_network,
+        );
+        user.halt();
+        simulator.add_manual_road_users(user);
+        while simulator.current_time() < 3.0 {
+            simulator.tick(0.1);
+        }
+        assert_eq!(simulator.detectors()[0].occupancy(), 1.0);
+        assert_eq!(simulator.current_speed_limit(0), 6.0);
+
+        simulator.current_road_users.clear();
+        let mut changes = vec![(simulator.current_time(), 6.0)];
+        while simulator.current_time() < 20.0 {
+            simulator.tick(0.1);
+            let limit = simulator.current_speed_limit(1);
+            if changes.last().unwrap().1 != limit {
+                changes.push((simulator.current_time(), limit));
+            }
+        }
+
+        // The occupancy drops below 0.5 after 6 seconds and below 0.2 once the occupied samples
+        // leave the window, but every new limit is held for 5 seconds
+        let limits = changes.iter().map(|(_, limit)| *limit).collect::<Vec<_>>();
+        assert_eq!(limits, [6.0, 10.0, 50.0 / 3.6]);
+        assert!((6.0..6.5).contains(&changes[1].0));
+        assert!((11.0..11.5).contains(&changes[2].0));
+        assert_eq!(simulator.detectors()[0].occupancy(), 0.0);
+    }
+
+    #[test]
+    fn detector_measures_a_passing_vehicle() {
+        let mut simulator = Simulator::new(straight_road(3, 100.0), Vec::new());
+        simulator.set_driver_distribution(DriverDistribution::identical());
+        simulator.add_detector(Detector::new(0, 1, 5.0));
+        simulator
+            .inject_vehicle(0, 2, VehicleClass::PassengerCar)
+            .unwrap();
+
+        while simulator.current_road_users()[0].location().x < 110.0 {
+            simulator.tick(0.05);
+        }
+
+        // The car of 4.5 m passes at 50 km/h, which takes about a third of a second
+        let detector = &simulator.detectors()[0];
+        assert_eq!(detector.vehicle_count(), 1);
+        let expected_occupancy = 4.5 / (50.0 / 3.6) / 5.0;
+        assert!((detector.occupancy() - expected_occupancy).abs() < 0.011);
+
+        while simulator.current_time() < 30.0 {
+            simulator.tick(0.05);
+        }
+        assert_eq!(simulator.detectors()[0].vehicle_count(), 1);
+        assert_eq!(simulator.detectors()[0].occupancy(), 0.0);
+    }
+
+    #[test]
+    fn fixed_rate_ramp_meter_releases_its_rate() {
+        let mut simulator = Simulator::new(
+            RoadNetwork::new(
+                [(0, 0.0), (1, 200.0), (2, 300.0), (3, 400.0)]
+                    .into_iter()
+                    .map(|(id, x)| {
+                        (
+                            id,
+                            Node::new(
+                                id,
+                                Point3::new(x, 0.0, 0.0),
+                                50.0 / 3.6,
+                                if id < 3 { vec![id + 1] } else { Vec::new() },
+                                None,
+                                None,
+                            ),
+                        )
+                    })
+                    .collect(),
+            ),
+            vec![Box::new(FixedRateRampMeter::new(1, 900.0, 1.0))],
+        );
+        simulator.set_driver_distribution(DriverDistribution::identical());
+        simulator.add_demand(TrafficDemand::new(0, 0, 3, 1800.0));
+        simulator.add_detector(Detector::new(0, 2, 10.0));
+
+        // Wait for the queue in front of the meter to form
+        while simulator.current_time() < 60.0 {
+            simulator.tick(0.1);
+        }
+        let count_before = simulator.detectors()[0].vehicle_count();
+
+        // A twentieth of an hour
+        while simulator.current_time() < 240.0 {
+            simulator.tick(0.1);
+        }
+        let released = simulator.detectors()[0].vehicle_count() - count_before;
+        assert!((44..=46).contains(&released), "{released} vehicles");
+    }
+
+    #[test]
+    fn alinea_ramp_meter_follows_the_occupancy() {
+        let network = straight_road(2, 100.0);
+        let mut parked_on_detector =
+            RoadUser::new(0, Point3::origin(), 0.0, 3.5, 5.0, PI / 2.0, 1, 1, &network);
+        parked_on_detector.halt();
+
+        // Occupied for 3 of the 10 seconds in the window
+        let mut busy = Detector::new(0, 0, 10.0);
+        for step in 0..100 {
+            let road_users = if step < 70 {
+                &[][..]
+            } else {
+                std::slice::from_ref(&parked_on_detector)
+            };
+            busy.tick(step as f32 * 0.1, 0.1, &network, road_users);
+        }
+        assert!((busy.occupancy() - 0.3).abs() < 1e-3);
+        let idle = Detector::new(0, 0, 10.0);
+
+        let mut meter = AlineaRampMeter::new(1, vec![0], 0.2, 7000.0, 300.0, 1800.0, 60.0, 2.0);
+        let mut rates = Vec::new();
+        for minute in 1..=5 {
+            let detectors = if minute <= 3 { &busy } else { &idle };
+            meter.observe_detectors(std::slice::from_ref(detectors));
+            meter.tick(minute as f32 * 60.0 - 30.0);
+            assert_eq!(rates.last().copied().unwrap_or(1800.0), meter.rate());
+            meter.tick(minute as f32 * 60.0);
+            rates.push(meter.rate());
+        }
+
+        // 0.1 above the target lowers the rate by 700 veh/h, 0.2 below it raises it by 1400
+        for (rate, expected) in rates
+            .into_iter()
+            .zip([1100.0, 400.0, 300.0, 1700.0, 1800.0])
+        {
+            assert!((rate - expected).abs() < 0.01, "{rate} veh/h");
+        }
+    }
+
+    /// The parking events of every road user, in order
+    fn parking_events(events: &[SimulationEvent]) -> HashMap<u32, Vec<SimulationEvent>> {
+        let mut per_road_user = HashMap::<u32, Vec<SimulationEvent>>::new();
+        for event in events {
+            let road_user = match event {
+                SimulationEvent::Parked { road_user, .. }
+                | SimulationEvent::ParkingFull { road_user, .. }
+                | SimulationEvent::ParkingNotFound { road_user }
+                | SimulationEvent::LeftParking { road_user, .. } => *road_user,
+                _ => continue,
+            };
+            per_road_user
+                .entry(road_user)
+                .or_default()
+                .push(event.clone());
+        }
+        per_road_user
+    }
+
+    #[test]
+    fn trip_chain_parks_and_continues_with_the_next_leg() {
+        let mut simulator = Simulator::new(straight_road(4, 100.0), Vec::new());
+        simulator.add_parking_facility(ParkingFacility::new(0, 2, 1));
+        simulator.add_parking_facility(ParkingFacility::new(1, 3, 1));
+        simulator.add_manual_road_users(
+            RoadUser::new(
+                0,
+                Point3::new(-1.0, 0.0, 0.0),
+                0.0,
+                3.5,
+                5.0,
+                PI / 2.0,
+                0,
+                3,
+                &simulator.road_network,
+            )
+            .with_trip_chain(vec![TripLeg::new(2, 30.0), TripLeg::new(3, 10.0)]),
+        );
+
+        let mut events = Vec::new();
+        while simulator.parked_road_users().count() == 0 {
+            simulator.tick(0.1);
+            events.extend_from_slice(simulator.events());
+        }
+        let parked_at = simulator.current_time();
+        assert_eq!(simulator.parking_facilities()[0].occupied(), 1);
+
+        // Someone stands in front of the facility when the parking time is over
+        let mut blocking = RoadUser::new(
+            1,
+            simulator.road_network.find_node(2).location(),
+            0.0,
+            3.5,
+            5.0,
+            PI / 2.0,
+            3,
+            3,
+            &simulator.road_network,
+        );
+        blocking.halt();
+        simulator.add_manual_road_users(blocking);
+        while simulator.current_time() < parked_at + 40.0 {
+            simulator.tick(0.1);
+            events.extend_from_slice(simulator.events());
+        }
+        assert_eq!(simulator.parking_facilities()[0].occupied(), 1);
+
+        simulator.current_road_users.clear();
+        while simulator.current_time() < parked_at + 100.0 {
+            simulator.tick(0.1);
+            events.extend_from_slice(simulator.events());
+        }
+
+        assert_eq!(
+            parking_events(&events)[&0],
+            [
+                SimulationEvent::Parked {
+                    road_user: 0,
+                    facility: 0
+                },
+                SimulationEvent::LeftParking {
+                    road_user: 0,
+                    facility: 0
+                },
+                SimulationEvent::Parked {
+                    road_user: 0,
+                    facility: 1
+                },
+            ]
+        );
+        assert_eq!(simulator.parked_road_users().count(), 0);
+        assert!(simulator.current_road_users().is_empty());
+        assert!(simulator
+            .parking_facilities()
+            .iter()
+            .all(|facility| facility.occupied() == 0));
+    }
+
+    #[test]
+    fn full_parking_facility_sends_vehicles_elsewhere() {
+        let mut simulator = Simulator::new(straight_road(4, 100.0), Vec::new());
+        simulator.add_parking_facility(ParkingFacility::new(0, 1, 1));
+        simulator.add_parking_facility(ParkingFacility::new(1, 2, 1));
+        for time in [0.0, 5.0, 10.0] {
+            simulator.schedule_departure(
+                time,
+                RoadUser::new(
+                    0,
+                    Point3::new(-1.0, 0.0, 0.0),
+                    0.0,
+                    3.5,
+                    5.0,
+                    PI / 2.0,
+                    0,
+                    3,
+                    &simulator.road_network,
+                )
+                .with_trip_chain(vec![TripLeg::new(1, 60.0), TripLeg::new(3, 0.0)]),
+            );
+        }
+
+        let mut events = Vec::new();
+        while simulator.current_time() < 200.0 {
+            simulator.tick(0.1);
+            events.extend_from_slice(simulator.events());
+        }
+        let events = parking_events(&events);
+
+        // The first one gets the last space at its destination
+        assert_eq!(
+            events[&0],
+            [
+                SimulationEvent::Parked {
+                    road_user: 0,
+                    facility: 0
+                },
+                SimulationEvent::LeftParking {
+                    road_user: 0,
+                    facility: 0
+                },
+                SimulationEvent::ParkingNotFound { road_user: 0 },
+            ]
+        );
+        // The second one finds a space further down the road
+        assert_eq!(
+            events[&1],
+            [
+                SimulationEvent::ParkingFull {
+                    road_user: 1,
+                    facility: 0
+                },
+                SimulationEvent::Parked {
+                    road_user: 1,
+                    facility: 1
+                },
+                SimulationEvent::LeftParking {
+                    road_user: 1,
+                    facility: 1
+                },
+                SimulationEvent::ParkingNotFound { road_user: 1 },
+            ]
+        );
+        // The last one finds no space at all, but still drives the rest of its trip chain
+        assert_eq!(
+            events[&2][0],
+            SimulationEvent::ParkingFull {
+                road_user: 2,
+                facility: 0
+            }
+        );
+        assert!(!events[&2]
+            .iter()
+            .any(|event| matches!(event, SimulationEvent::Parked { .. })));
+        assert_eq!(
+            events[&2]
+                .iter()
+                .filter(|event| matches!(event, SimulationEvent::ParkingNotFound { .. }))
+                .count(),
+            2
+        );
+
+        assert!(simulator.current_road_users().is_empty());
+        assert_eq!(simulator.parked_road_users().count(), 0);
+    }
+
+    #[test]
+    fn bad_weather_makes_drivers_keep_longer_gaps() {
+        let time_gap = |condition| {
+            let mut simulator = Simulator::new(straight_road(2, 2000.0), Vec::new());
+            simulator.set_weather(Weather::new(WeatherSchedule::constant(condition)));
+            for (id, x, desired_speed_factor) in [(0, 100.0, 0.5), (1, 0.0, 1.0)] {
+                simulator.add_manual_road_users(
+                    RoadUser::new(
+                        id,
+                        Point3::new(x, 0.0, 0.0),
+                        0.0,
+                        3.5,
+                        5.0,
+                        PI / 2.0,
+                        1,
+                        1,
+                        &simulator.road_network,
+                    )
+                    .with_driver_behaviour(DriverBehaviour {
+                        desired_speed_factor,
+                        ..Default::default()
+                    }),
+                );
+            }
+
+            for _ in 0..1200 {
+                simulator.tick(0.1);
+            }
+
+            let [leader, follower] = simulator.current_road_users() else {
+                panic!("Both cars should still be driving");
+            };
+            let free_gap = leader.location().x - follower.location().x - follower.length() - 2.0;
+            free_gap / follower.current_speed()
+        };
+
+        // The time headway of 1.5 s times the headway factor of the condition
+        assert!((time_gap(WeatherCondition::Dry) - 1.5).abs() < 0.05);
+        assert!((time_gap(WeatherCondition::Wet) - 1.8).abs() < 0.05);
+        assert!((time_gap(WeatherCondition::Fog) - 2.1).abs() < 0.05);
+    }
+
+    #[test]
+    fn weather_zone_only_affects_vehicles_inside_it() {
+        let mut simulator = Simulator::new(straight_road(2, 1000.0), Vec::new());
+        let fog_bank = Point3::new(500.0, 0.0, 0.0);
+        let mut weather = Weather::new(WeatherSchedule::constant(WeatherCondition::Dry));
+        weather.add_zone(WeatherZone::new(
+            fog_bank,
+            100.0,
+            WeatherSchedule::new(vec![(10.0, WeatherCondition::Fog)]),
+        ));
+        simulator.set_weather(weather);
+
+        // The fog only comes in after 10 seconds
+        assert_eq!(
+            simulator.weather().condition_at(&fog_bank, 5.0),
+            WeatherCondition::Dry
+        );
+        assert_eq!(
+            simulator.weather().condition_at(&fog_bank, 15.0),
+            WeatherCondition::Fog
+        );
+
+        simulator.add_manual_road_users(
+            RoadUser::new(
+                0,
+                Point3::new(-1.0, 0.0, 0.0),
+                0.0,
+                3.5,
+                5.0,
+                PI / 2.0,
+                0,
+                1,
+                &simulator.road_network,
+            )
+            .with_driver_behaviour(DriverBehaviour::default()),
+        );
+
+        let mut speeds = Vec::new();
+        while let Some(user) = simulator.current_road_users().first() {
+            speeds.push((user.location().x, user.current_speed()));
+            simulator.tick(0.1);
+        }
+        let drives_at = |from, to, expected_speed: f32| {
+            speeds
+                .iter()
+                .filter(|(x, _)| (from..to).contains(x))
+                .all(|(_, speed)| (speed - expected_speed).abs() < 0.01)
+        };
+
+        // Drivers slow down to 70% of the speed limit in fog
+        assert!(drives_at(100.0, 400.0, 50.0 / 3.6));
+        assert!(drives_at(450.0, 600.0, 50.0 / 3.6 * 0.7));
+        assert!(drives_at(650.0, 1000.0, 50.0 / 3.6));
+    }
+
+    #[test]
+    fn cars_only_slow_down_for_real_corners() {
+        // Straight on over short edges up to node 10, then a right angle turn onto a short edge
+        let mut nodes = (0..=10)
+            .map(|id| (id, Point3::new(id as f32 * 20.0, 0.0, 0.0)))
+            .collect::<Vec<_>>();
+        nodes.push((11, Point3::new(200.0, -10.0, 0.0)));
+        nodes.push((12, Point3::new(200.0, -100.0, 0.0)));
+        let mut simulator = Simulator::new(
+            RoadNetwork::new(
+                nodes
+                    .into_iter()
+                    .map(|(id, location)| {
+                        (
+                            id,
+                            Node::new(
+                                id,
+                                location,
+                                50.0 / 3.6,
+                                if id < 12 { vec![id + 1] } else { Vec::new() },
+                                None,
+                                None,
+                            ),
+                        )
+                    })
+                    .collect(),
+            ),
+            Vec::new(),
+        );
+        simulator.set_driver_distribution(DriverDistribution::identical());
+        simulator
+            .inject_vehicle(0, 12, VehicleClass::PassengerCar)
+            .unwrap();
+
+        let mut path = Vec::new();
+        while simulator.current_road_users()[0].next_node() <= 10 {
+            let user = &simulator.current_road_users()[0];
+            path.push((user.location().x, user.current_speed()));
+            simulator.tick(0.05);
+        }
+
+        assert!(path
+            .iter()
+            .filter(|(x, _)| (50.0..170.0).contains(x))
+            .all(|(_, speed)| (speed - 50.0 / 3.6).abs() < 0.01));
+        // Turning a quarter at the maximum steering angle takes a second, in which the car
+        // can't drive further than the 10 m to the next node
+        let (_, corner_speed) = path.last().unwrap();
+        assert!(
+            *corner_speed <= 10.5,
+            "Took the corner at {corner_speed} m/s"
+        );
+    }
+
+    #[test]
+    fn long_steps_do_not_jump_over_nodes() {
+        let mut simulator = Simulator::new(straight_road(10, 20.0), Vec::new());
+        simulator
+            .inject_vehicle(0, 9, VehicleClass::PassengerCar)
+            .unwrap();
+
+        // Steps of 7 m at full speed, longer than the distance at which a node counts as reached
+        while !simulator.current_road_users().is_empty() {
+            simulator.tick(0.5);
+            assert!(simulator.current_time() < 60.0, "The car doesn't arrive");
+        }
+    }
+
+    fn sloped_road(grade: f32) -> RoadNetwork {
+        RoadNetwork::new(
+            (0..11)
+                .map(|id| {
+                    let distance = id as f32 * 100.0;
+                    (
+                        id,
+                        Node::new(
+                            id,
+                            Point3::new(distance, 0.0, distance * grade),
+                            80.0 / 3.6,
+                            if id < 10 { vec![id + 1] } else { Vec::new() },
+                            None,
+                            None,
+                        ),
+                    )
+                })
+                .collect(),
+        )
+    }
+
+    #[test]
+    fn trucks_lose_speed_on_climbs() {
+        let top_speed = |grade: f32| {
+            let mut simulator = Simulator::new(sloped_road(grade), Vec::new());
+            simulator.set_driver_distribution(DriverDistribution::identical());
+            simulator
+                .inject_vehicle(0, 10, VehicleClass::Truck)
+                .unwrap();
+
+            let mut top_speed = 0.0f32;
+            while let Some(truck) = simulator.current_road_users().first() {
+                top_speed = top_speed.max(truck.current_speed());
+                simulator.tick(0.1);
+            }
+            top_speed
+        };
+
+        let flat = top_speed(0.0);
+        let climb = top_speed(0.1);
+        assert!((flat - 80.0 / 3.6).abs() < 0.01);
+        // The engine can't deliver more than P / (m g sin(a)) on the climb
+        let sin_grade = 0.1 / 1.01f32.sqrt();
+        let max_climb_speed = VehicleClass::Truck.power()
+            / (VehicleClass::Truck.mass() * vehicle::GRAVITY * sin_grade);
+        assert!(climb < max_climb_speed + 0.1, "Climbed at {climb} m/s");
+        assert!(climb < flat * 0.6);
+    }
+
+    #[test]
+    fn braking_downhill_takes_longer() {
+        let braking_time = |grade: f32| {
+            let mut simulator = Simulator::new(
+                sloped_road(grade),
+                vec![Box::new(TimedTrafficLight::new(
+                    5,
+                    vec![(1000.0, TrafficLightState::Red)],
+                ))],
+            );
+            simulator.set_driver_distribution(DriverDistribution::identical());
+            simulator
+                .inject_vehicle(0, 10, VehicleClass::PassengerCar)
+                .unwrap();
+
+            let mut braking_time = 0.0;
+            let mut previous_speed = 0.0;
+            for _ in 0..1000 {
+                simulator.tick(0.05);
+                let speed = simulator.current_road_users()[0].current_speed();
+                if speed < previous_speed {
+                    braking_time += 0.05;
+                }
+                previous_speed = speed;
+            }
+            assert_eq!(previous_speed, 0.0);
+            braking_time
+        };
+
+        let flat = braking_time(0.0);
+        let downhill = braking_time(-0.1);
+        assert!(
+            downhill > flat * 1.1,
+            "Braked {downhill} s against {flat} s"
+        );
+    }
+
+    #[test]
+    fn unroutable_demand_is_reported() {
+        let mut simulator = Simulator::new(straight_road(3, 100.0), Vec::new());
+        // The road only leads from 0 to 2
+        simulator.add_demand(TrafficDemand::new(7, 2, 0, 3600.0));
+
+        let mut events = Vec::new();
+        for _ in 0..50 {
+            simulator.tick(0.1);
+            events.extend_from_slice(simulator.events());
+        }
+
+        assert!(simulator.current_road_users().is_empty());
+        assert_eq!(events.len(), 5);
+        assert!(events
+            .iter()
+            .all(|event| *event == SimulationEvent::DemandUnroutable { demand: 7 }));
+    }
+
+    #[test]
+    fn emissions_are_aggregated() {
+        let mut simulator = Simulator::new(
+            RoadNetwork::new(
+                (0..3)
+                    .map(|id| {
+                        (
+                            id,
+                            Node::new(
+                                id,
+                                Point3::new(id as f32 * 100.0, 0.0, 0.0),
+                                50.0 / 3.6, // 50kph
+                                if id < 2 { vec![id + 1] } else { Vec::new() },
+                                None,
+                                None,
+                            ),
+                        )
+                    })
+                    .collect(),
+            ),
+            Vec::new(),
+        );
+
+        let user = RoadUser::new(
+            0,
+            Point3::new(-10.0, 0.0, 0.0),
+            0.0,
+            3.5,
+            5.0,
+            PI / 2.0,
+            0,
+            2,
+            &simulator.road_network,
+        );
+        simulator.add_manual_road_users(user);
+
+        while !simulator.current_road_users().is_empty() {
+            simulator.tick(0.05);
+        }
+
+        let emissions = simulator.emissions();
+        let total = emissions.total();
+        assert!(total.fuel > 0.0 && total.co2 > 0.0 && total.nox > 0.0);
+        assert_eq!(emissions.road_user(0), total);
+        assert!(emissions.edge(0, 1).fuel > 0.0);
+        assert!(emissions.edge(1, 2).fuel > 0.0);
+        assert!(emissions.edge(0, 1).fuel + emissions.edge(1, 2).fuel <= total.fuel);
+    }
+
+    #[test]
+    fn electric_car_detours_to_charger() {
+        let mut simulator = Simulator::new(
+            RoadNetwork::new(
+                [
+                    (0, Point3::new(0.0, 0.0, 0.0), vec![1]),
+                    (1, Point3::new(100.0, 0.0, 0.0), vec![2, 4]),
+                    (2, Point3::new(200.0, 0.0, 0.0), vec![3]),
+                    (3, Point3::new(300.0, 0.0, 0.0), Vec::new()),
+                    (4, Point3::new(150.0, 30.0, 0.0), vec![2]),
+                ]
+                .into_iter()
+                .map(|(id, location, next_nodes)| {
+                    (
+                        id,
+                        Node::new(id, location, 50.0 / 3.6, next_nodes, None, None),
+                    )
+                })
+                .collect(),
+            ),
+            Vec::new(),
+        );
+        simulator.add_charging_station(ChargingStation::new(0, 4, 150.0, 1));
+
+        let user = RoadUser::new(
+            0,
+            Point3::new(-1.0, 0.0, 0.0),
+            0.0,
+            3.5,
+            5.0,
+            PI / 2.0,
+            0,
+            3,
+            &simulator.road_network,
+        )
+        .with_class(VehicleClass::ElectricCar)
+        .with_battery(Battery::new(10.0, 0.15));
+        simulator.add_manual_road_users(user);
+
+        let mut events = Vec::new();
+        for _ in 0..4000 {
+            simulator.tick(0.1);
+            events.extend_from_slice(simulator.events());
+        }
+
+        assert_eq!(
+            events,
+            [
+                SimulationEvent::DetouredToCharger {
+                    road_user: 0,
+                    station: 0
+                },
+                SimulationEvent::ChargingStarted {
+                    road_user: 0,
+                    station: 0
+                },
+                SimulationEvent::ChargingFinished {
+                    road_user: 0,
+                    station: 0
+                },
+            ]
+        );
+        assert!(simulator.current_road_users().is_empty());
+        assert_eq!(simulator.emissions().total(), Default::default());
+    }
+
+    #[test]
+    fn electric_car_arriving_with_low_battery_doesnt_detour() {
+        let mut simulator = Simulator::new(straight_road(3, 100.0), Vec::new());
+        simulator.add_charging_station(ChargingStation::new(0, 2, 150.0, 1));
+
+        // The car reaches its destination on the same tick its battery turns low
+        let user = RoadUser::new(
+            0,
+            Point3::new(99.6, 0.0, 0.0),
+            0.0,
+            3.5,
+            5.0,
+            PI / 2.0,
+            1,
+            1,
+            &simulator.road_network,
+        )
+        .with_class(VehicleClass::ElectricCar)
+        .with_battery(Battery::new(10.0, 0.2));
+        simulator.add_manual_road_users(user);
+        simulator.tick(0.1);
+
+        assert!(simulator.events().is_empty());
+        assert!(simulator.current_road_users().is_empty());
+        assert_eq!(simulator.charging_road_users().count(), 0);
+    }
+
+    #[test]
+    fn electric_car_remembers_unreachable_chargers() {
+        let mut simulator = Simulator::new(straight_road(4, 100.0), Vec::new());
+        // The road only leads away from the charger
+        simulator.add_charging_station(ChargingStation::new(0, 0, 150.0, 1));
+
+        let user = RoadUser::new(
+            0,
+            Point3::new(99.0, 0.0, 0.0),
+            0.0,
+            3.5,
+            5.0,
+            PI / 2.0,
+            1,
+            3,
+            &simulator.road_network,
+        )
+        .with_class(VehicleClass::ElectricCar)
+        .with_battery(Battery::new(10.0, 0.15));
+        simulator.add_manual_road_users(user);
+        simulator.tick(0.1);
+
+        assert!(simulator.events().is_empty());
+        assert!(!simulator.current_road_users()[0].needs_charging());
+
+        simulator.add_charging_station(ChargingStation::new(1, 2, 150.0, 1));
+        simulator.tick(0.1);
+        assert_eq!(
+            simulator.events(),
+            [SimulationEvent::DetouredToCharger {
+                road_user: 0,
+                station: 1
+            }]
+        );
+    }
+
+    #[test]
+    fn closed_edge_holds_traffic_until_reopened() {
+        let mut simulator = Simulator::new(
+            RoadNetwork::new(
+                (0..4)
+                    .map(|id| {
+                        (
+                            id,
+                            Node::new(
+                                id,
+                                Point3::new(id as f32 * 100.0, 0.0, 0.0),
+                                50.0 / 3.6,
+                                if id < 3 { vec![id + 1] } else { Vec::new() },
+                                None,
+                                None,
+                            ),
+                        )
+                    })
+                    .collect(),
+            ),
+            Vec::new(),
+        );
+        simulator.add_incident(Incident::new(
+            0,
+            IncidentLocation::Edge { from: 1, to: 2 },
+            IncidentImpact::Closed,
+            0.0,
+            None,
+        ));
+        simulator.add_manual_road_users(RoadUser::new(
+            0,
+            Point3::new(-1.0, 0.0, 0.0),
+            0.0,
+            3.5,
+            5.0,
+            PI / 2.0,
+            0,
+            3,
+            &simulator.road_network,
+        ));
+
+        for _ in 0..300 {
+            simulator.tick(0.1);
+        }
+
+        let user = &simulator.current_road_users()[0];
+        assert!(user.location().x <= 100.0);
+        assert!(user.current_speed() < 0.1);
+
+        assert!(simulator.end_incident(0));
+        for _ in 0..300 {
+            simulator.tick(0.1);
+        }
+
+        assert!(simulator.closures().is_empty());
+        assert!(simulator.current_road_users().is_empty());
+    }
+
+    #[test]
+    fn incidents_at_one_node_dont_add_up() {
+        let mut simulator = Simulator::new(straight_road(3, 100.0), Vec::new());
+        for (id, location, speed_factor) in [
+            (0, IncidentLocation::Node(1), 0.5),
+            (1, IncidentLocation::Edge { from: 0, to: 1 }, 0.8),
+        ] {
+            simulator.add_incident(Incident::new(
+                id,
+                location,
+                IncidentImpact::ReducedCapacity { speed_factor },
+                0.0,
+                None,
+            ));
+        }
+
+        for _ in 0..3 {
+            simulator.tick(0.1);
+        }
+        assert_eq!(simulator.current_speed_limit(1), 50.0 / 3.6 * 0.5);
+    }
+
+    #[test]
+    fn scenario_timeline_is_executed() {
+        let scenario = Scenario::from_json(
+            r#"{
+                "nodes": [
+                    { "id": 0, "location": [0, 0, 0], "max_speed": 13.9, "next_nodes": [1] },
+                    { "id": 1, "location": [100, 0, 0], "max_speed": 13.9, "next_nodes": [2] },
+                    { "id": 2, "location": [200, 0, 0], "max_speed": 13.9 }
+                ],
+                "traffic_lights": [{ "node": 1, "schema": [[10, "Green"]] }],
+                "demand": [{ "id": 0, "origin": 0, "destination": 2, "rate": 0 }],
+                "timeline": [
+                    { "time": 0, "action": { "type": "inject_vehicles", "vehicles": [
+                        { "origin": 0, "destination": 2, "class": "Truck" }
+                    ] } },
+                    { "time": 1, "action": { "type": "set_speed_limit", "nodes": [1], "speed_limit": 8.3 } },
+                    { "time": 1, "action": { "type": "set_traffic_light_schema", "node": 1,
+                        "schema": [[10, "Red"]] } },
+                    { "time": 2, "action": { "type": "close_node", "node": 2, "duration": 10 } },
+                    { "time": 5, "action": { "type": "set_demand_rate", "demand": 0, "rate": 3600 } }
+                ]
+            }"#,
+        )
+        .unwrap();
+        let mut simulator = scenario.into_simulator();
+
+        simulator.tick(0.1);
+        assert_eq!(simulator.current_road_users().len(), 1);
+        assert_eq!(
+            simulator.current_road_users()[0].class(),
+            VehicleClass::Truck
+        );
+
+        while simulator.current_time() < 3.0 {
+            simulator.tick(0.1);
+        }
+        assert_eq!(simulator.current_speed_limit(1), 8.3);
+        assert_eq!(
+            simulator.traffic_lights()[0].get_state(),
+            TrafficLightState::Red
+        );
+        assert!(simulator.closures().is_node_closed(2));
+
+        while simulator.current_time() < 60.0 {
+            simulator.tick(0.1);
+        }
+        assert!(simulator.scheduled_events().is_empty());
+        assert!(simulator.closures().is_empty());
+        assert!(simulator.current_road_users().len() > 1);
+
+        assert!(matches!(
+            Scenario::from_json(
+                r#"{ "nodes": [{ "id": 0, "location": [0, 0, 0], "max_speed": 1, "next_nodes": [7] }] }"#
+            ),
+            Err(scenario::ScenarioError::UnknownNode(7))
+        ));
+    }
+
+    /// Two lanes into a signalised junction and one lane out of it, plus a bicycle lane
+    const SUMO_NETWORK: &str = r#"<net version="1.9">
+        <edge id=":J_0" function="internal">
+            <lane id=":J_0_0" index="0" speed="13.89" length="10" shape="100,-4.8 110,-1.6"/>
+        </edge>
+        <edge id="in" from="A" to="J">
+            <lane id="in_0" index="0" speed="13.89" length="100" shape="0,-4.8 100,-4.8"/>
+            <lane id="in_1" index="1" speed="13.89" length="100" shape="0,-1.6 100,-1.6"/>
+            <lane id="in_2" index="2" allow="bicycle" speed="5" length="100" shape="0,1.6 100,1.6"/>
+        </edge>
+        <edge id="out" from="J" to="B">
+            <lane id="out_0" index="0" speed="13.89" length="90" shape="110,-1.6 200,-1.6"/>
+        </edge>
+        <tlLogic id="J" type="static" programID="0" offset="10">
+            <phase duration="30" state="GG"/>
+            <phase duration="3" state="yy"/>
+            <phase duration="30" state="rr"/>
+        </tlLogic>
+        <connection from="in" to="out" fromLane="0" toLane="0" via=":J_0_0" tl="J" linkIndex="0"/>
+        <connection from="in" to="out" fromLane="1" toLane="0" tl="J" linkIndex="1"/>
+        <connection from=":J_0" to="out" fromLane="0" toLane="0"/>
+    </net>"#;
+
+    #[test]
+    fn sumo_network_is_imported() {
+        let sumo = SumoNetwork::from_xml(SUMO_NETWORK).unwrap();
+
+        let network = sumo.road_network();
+        let right_lane = sumo.lane_nodes("in_0").unwrap().to_vec();
+        let left_lane = sumo.lane_nodes("in_1").unwrap().to_vec();
+        let exit_lane = sumo.lane_nodes("out_0").unwrap().to_vec();
+        assert_eq!(
+            sumo.edge_lanes("in").unwrap(),
+            [(0, "in_0".to_string()), (1, "in_1".to_string())]
+        );
+        assert!(sumo.lane_nodes("in_2").is_none());
+        assert_eq!(
+            network.find_node(right_lane[1]).adjacent_node_left_id(),
+            Some(left_lane[1])
+        );
+        assert_eq!(
+            network.find_node(left_lane[0]).adjacent_node_right_id(),
+            Some(right_lane[0])
+        );
+
+        // Both lanes get their own light on a node just behind the end of the lane
+        assert_eq!(sumo.traffic_lights().len(), 2);
+        let link_node = network.find_node(network.find_node(right_lane[1]).next_node_ids()[0]);
+        assert!(sumo
+            .traffic_lights()
+            .iter()
+            .any(|light| light.node() == link_node.id));
+        assert_eq!(link_node.next_node_ids(), [exit_lane[0]]);
+
+        // With the offset the program is in its red phase for the first 10 seconds
+        let mut simulator = sumo.into_simulator();
+        simulator.tick(0.1);
+        assert!(simulator
+            .traffic_lights()
+            .iter()
+            .all(|light| light.get_state() == TrafficLightState::Red));
+
+        simulator
+            .inject_vehicle(right_lane[0], exit_lane[1], VehicleClass::PassengerCar)
+            .unwrap();
+        while simulator.current_time() < 9.0 {
+            simulator.tick(0.1);
+        }
+        assert!(simulator.current_road_users()[0].location().x < 101.0);
+
+        while simulator.current_time() < 40.0 {
+            simulator.tick(0.1);
+        }
+        assert!(simulator.current_road_users().is_empty());
+    }
+
+    #[test]
+    fn sumo_routes_are_imported() {
+        let network = SumoNetwork::from_xml(SUMO_NETWORK).unwrap();
+        let routes = SumoRoutes::from_xml(
+            r#"<routes>
+                <vType id="bus" vClass="bus" length="12" accel="1.0" tau="2"/>
+                <route id="main" edges="in out"/>
+                <vehicle id="bus_0" type="bus" route="main" depart="0" departLane="1"/>
+                <flow id="cars" begin="0" end="20" period="5" departLane="0">
+                    <route edges="in out"/>
+                </flow>
+                <trip id="trip_0" depart="3" from="in" to="out"/>
+            </routes>"#,
+            &network,
+        )
+        .unwrap();
+
+        let departures = routes.departures().collect::<Vec<_>>();
+        assert_eq!(
+            departures.iter().map(|(time, _)| *time).collect::<Vec<_>>(),
+            [0.0, 0.0, 3.0, 5.0, 10.0, 15.0]
+        );
+        let (_, bus) = departures
+            .iter()
+            .find(|(_, user)| user.class() == VehicleClass::Bus)
+            .unwrap();
+        assert_eq!(bus.length(), 12.0);
+        assert_eq!(bus.driver_behaviour().time_headway, 2.0);
+        assert_eq!(
+            bus.location(),
+            network
+                .road_network()
+                .find_node(network.lane_nodes("in_1").unwrap()[0])
+                .location()
+        );
+
+        assert!(matches!(
+            SumoRoutes::from_xml(
+                r#"<routes><vehicle id="v" route="missing" depart="0"/></routes>"#,
+                &network
+            ),
+            Err(SumoError::UnknownRoute(_))
+        ));
+
+        let mut simulator = network.into_simulator();
+        routes.schedule(&mut simulator);
+        while simulator.current_time() < 120.0 {
+            simulator.tick(0.1);
+        }
+        assert_eq!(simulator.pending_departures().count(), 0);
+        assert!(simulator.current_road_users().is_empty());
+    }
+
+    #[test]
+    fn sumo_depart_lane_is_the_sumo_lane_index() {
+        let network = SumoNetwork::from_xml(
+            r#"<net version="1.9">
+                <edge id="in" from="A" to="B">
+                    <lane id="in_0" index="0" allow="pedestrian" speed="2" length="100" shape="0,-8 100,-8"/>
+                    <lane id="in_1" index="1" speed="13.89" length="100" shape="0,-4.8 100,-4.8"/>
+                    <lane id="in_2" index="2" speed="13.89" length="100" shape="0,-1.6 100,-1.6"/>
+                </edge>
+            </net>"#,
+        )
+        .unwrap();
+        let lane_start = |lane: &str| {
+            network
+                .road_network()
+                .find_node(network.lane_nodes(lane).unwrap()[0])
+                .location()
+        };
+
+        let routes = SumoRoutes::from_xml(
+            r#"<routes>
+                <vehicle id="route" depart="0" departLane="2"><route edges="in"/></vehicle>
+                <trip id="trip" depart="0" from="in" to="in" departLane="1"/>
+            </routes>"#,
+            &network,
+        )
+        .unwrap();
+        let departures = routes.departures().collect::<Vec<_>>();
+        assert_eq!(departures[0].1.location(), lane_start("in_2"));
+        assert_eq!(departures[1].1.location(), lane_start("in_1"));
+
+        // The sidewalk isn't imported, so vehicles can't depart from it
+        for routes in [
+            r#"<routes><vehicle id="v" depart="0" departLane="0"><route edges="in"/></vehicle></routes>"#,
+            r#"<routes><trip id="t" depart="0" from="in" to="in" departLane="0"/></routes>"#,
+        ] {
+            assert!(matches!(
+                SumoRoutes::from_xml(routes, &network),
+                Err(SumoError::InvalidValue {
+                    attribute: "departLane",
+                    ..
+                })
+            ));
+        }
+    }
+
+    #[test]
+    fn trajectories_are_exported() {
+        let mut simulator = Simulator::new(
+            RoadNetwork::new(
+                (0..2)
+                    .map(|id| {
+                        (
+                            id,
+                            Node::new(
+                                id,
+                                Point3::new(0.0, id as f32 * 200.0, 0.0),
+                                50.0 / 3.6,
+                                if id == 0 { vec![1] } else { Vec::new() },
+                                None,
+                                None,
+                            ),
+                        )
+                    })
+                    .collect(),
+            ),
+            Vec::new(),
+        );
+        simulator.record_trajectories(1.0);
+        simulator
+            .inject_vehicle(0, 1, VehicleClass::PassengerCar)
+            .unwrap();
+
+        for _ in 0..50 {
+            simulator.tick(0.1);
+        }
+
+        let trajectories = simulator.trajectories().unwrap();
+        let points = trajectories.road_user(0).collect::<Vec<_>>();
+        assert_eq!(points.len(), 5);
+        assert!(points.iter().all(|point| point.heading.abs() < 0.01));
+        assert!(points
+            .windows(2)
+            .all(|pair| pair[0].position.y < pair[1].position.y));
+
+        let mut csv = Vec::new();
+        trajectories.write_csv(&mut csv).unwrap();
+        let csv = String::from_utf8(csv).unwrap();
+        assert_eq!(csv.lines().count(), 6);
+        assert!(csv
+            .lines()
+            .nth(1)
+            .unwrap()
+            .starts_with("0.00,0,PassengerCar,0.00,0.00"));
+
+        let mut xml = Vec::new();
+        trajectories.write_fcd_xml(&mut xml).unwrap();
+        let xml = String::from_utf8(xml).unwrap();
+        assert_eq!(xml.matches("<timestep").count(), 5);
+        assert!(xml.contains(r#"<timestep time="1.00">"#));
+    }
+
+    #[test]
+    fn network_is_exported_to_geojson() {
+        let mut simulator = Simulator::new(
+            RoadNetwork::new(
+                (0..2)
+                    .map(|id| {
+                        (
+                            id,
+                            Node::new(
+                                id,
+                                Point3::new(0.0, id as f32 * 1000.0, 0.0),
+                                50.0 / 3.6,
+                                if id == 0 { vec![1] } else { Vec::new() },
+                                None,
+                                None,
+                            ),
+                        )
+                    })
+                    .collect(),
+            ),
+            Vec::new(),
+        );
+        simulator
+            .inject_vehicle(0, 1, VehicleClass::PassengerCar)
+            .unwrap();
+        let projection = Equirectangular::new(GeoPoint::new(52.0, 5.0, 0.0));
+
+        let network = geojson::network_features(simulator.road_network(), &projection);
+        let features = network["features"].as_array().unwrap();
+        assert_eq!(features.len(), 3);
+        assert_eq!(features[0]["geometry"]["coordinates"][1], 52.0);
+        let latitude = features[1]["geometry"]["coordinates"][1].as_f64().unwrap();
+        assert!((latitude - 52.00898).abs() < 1e-5);
+        assert_eq!(features[2]["geometry"]["type"], "LineString");
+        assert_eq!(features[2]["properties"]["to"], 1);
+
+        let local = projection.to_local(GeoPoint::new(latitude, 5.0, 0.0));
+        assert!((local.y - 1000.0).abs() < 0.01);
+
+        let road_users = geojson::road_user_features(
+            simulator.current_road_users(),
+            simulator.current_time(),
+            &projection,
+        );
+        assert_eq!(road_users["features"][0]["properties"]["heading"], 0.0);
+
+        let mut json = Vec::new();
+        geojson::write(&road_users, &mut json).unwrap();
+        assert!(String::from_utf8(json)
+            .unwrap()
+            .contains("\"FeatureCollection\""));
+    }
+
+    #[test]
+    fn coordinate_reference_converts_both_ways() {
+        let utm = TransverseMercator::utm(TransverseMercator::utm_zone(3.5), true);
+        let (x, y) = utm.project(45.0, 3.0);
+        assert!((x - 500_000.0).abs() < 0.01);
+        assert!((y - 4_982_950.40).abs() < 0.1);
+        let (latitude, longitude) = utm.unproject(x + 100_000.0, y);
+        let (x_back, _) = utm.project(latitude, longitude);
+        assert!((x_back - x - 100_000.0).abs() < 0.01);
+
+        let local = TransverseMercator::local(GeoPoint::new(52.0, 5.0, 0.0));
+        assert!(local.to_local(GeoPoint::new(52.0, 5.0, 0.0)).coords.norm() < 0.01);
+        let north = local.to_geographic(Point3::new(0.0, 1000.0, 0.0));
+        assert!((north.latitude - 52.00898).abs() < 1e-5);
+
+        let sumo = SumoNetwork::from_xml(&SUMO_NETWORK.replacen(
+            "<net version=\"1.9\">",
+            r#"<net version="1.9">
+            <location netOffset="-500000.00,-5761000.00" projParameter="+proj=utm +zone=31 +ellps=WGS84 +datum=WGS84 +units=m +no_defs"/>"#,
+            1,
+        ))
+        .unwrap();
+        let network = sumo.road_network();
+        assert_eq!(
+            network.coordinate_reference(),
+            Some(&TransverseMercator::utm(31, true).with_offset(-500_000.0, -5_761_000.0))
+        );
+        let origin = network.to_geographic(Point3::origin());
+        assert!((origin.longitude - 3.0).abs() < 1e-9);
+        assert!((network.to_local(origin) - Point3::origin()).norm() < 0.01);
+        assert_eq!(sumo.unsupported_projection(), None);
+
+        let mercator = "+proj=merc +datum=WGS84 +units=m +no_defs";
+        let unsupported = SumoNetwork::from_xml(&SUMO_NETWORK.replacen(
+            "<net version=\"1.9\">",
+            &format!(
+                r#"<net version="1.9"><location netOffset="0,0" projParameter="{mercator}"/>"#
+            ),
+            1,
+        ))
+        .unwrap();
+        assert_eq!(unsupported.road_network().coordinate_reference(), None);
+        assert_eq!(unsupported.unsupported_projection(), Some(mercator));
+
+        let features = geojson::network_features(network, network.projection());
+        let longitude = features["features"][0]["geometry"]["coordinates"][0]
+            .as_f64()
+            .unwrap();
+        assert!((longitude - 3.0).abs() < 0.01);
+
+        let mut scenario = Scenario::from_json(
+            r#"{ "nodes": [{ "id": 0, "location": [0, 0, 0], "max_speed": 13.9 }] }"#,
+        )
+        .unwrap();
+        scenario.coordinate_reference = Some(local);
+        let scenario = Scenario::from_json(&scenario.to_json().unwrap()).unwrap();
+        assert_eq!(scenario.road_network().coordinate_reference(), Some(&local));
+    }
+
+    #[test]
+    fn network_is_rendered_to_svg_and_dot() {
+        let simulator = Simulator::new(
+            RoadNetwork::new(
+                (0..4)
+                    .map(|id| {
+                        (
+                            id,
+                            Node::new(
+                                id,
+                                Point3::new((id / 2) as f32 * 100.0, (id % 2) as f32 * 3.2, 0.0),
+                                50.0 / 3.6,
+                                if id < 2 { vec![id + 2] } else { Vec::new() },
+                                if id % 2 == 1 { Some(id - 1) } else { None },
+                                if id % 2 == 0 { Some(id + 1) } else { None },
+                            ),
+                        )
+                    })
+                    .collect(),
+            ),
+            vec![Box::new(TimedTrafficLight::new(
+                2,
+                vec![(10.0, TrafficLightState::Red)],
+            ))],
+        );
+
+        let mut svg = Vec::new();
+        svg::write_network(
+            simulator.road_network(),
+            simulator.traffic_lights(),
+            &mut svg,
+        )
+        .unwrap();
+        let svg = String::from_utf8(svg).unwrap();
+        assert!(svg.contains(r#"viewBox="-10 -13.2 120 23.2""#));
+        assert_eq!(svg.matches("<line").count(), 2 + 2);
+        assert!(svg.contains(r#"<line x1="0" y1="-0" x2="99.5" y2="-0"/>"#));
+        assert!(svg.contains(r#"stroke="red""#));
+        assert!(svg.contains(">3</text>"));
+
+        let mut dot = Vec::new();
+        dot::write_network(simulator.road_network(), &mut dot).unwrap();
+        let dot = String::from_utf8(dot).unwrap();
+        assert!(dot.starts_with("digraph road_network {"));
+        assert!(dot.contains(r#"1 -> 3 [label="50"];"#));
+        assert!(dot.contains(r#"0 -> 1 [style=dashed, arrowhead=none, label="left"];"#));
+        assert!(dot.contains(r#"3 -> 2 [style=dashed, arrowhead=none, label="right"];"#));
+    }
+
+    #[test]
+    fn frames_are_rendered_to_png() {
+        let mut simulator = Simulator::new(
+            RoadNetwork::new(
+                (0..2)
+                    .map(|id| {
+                        (
+                            id,
+                            Node::new(
+                                id,
+                                Point3::new(id as f32 * 100.0, 0.0, 0.0),
+                                50.0 / 3.6,
+                                if id == 0 { vec![1] } else { Vec::new() },
+                                None,
+                                None,
+                            ),
+                        )
+                    })
+                    .collect(),
+            ),
+            vec![Box::new(TimedTrafficLight::new(
+                1,
+                vec![(10.0, TrafficLightState::Red)],
+            ))],
+        );
+        simulator
+            .inject_vehicle(0, 1, VehicleClass::PassengerCar)
+            .unwrap();
+
+        assert!(matches!(
+            FrameRenderer::new(simulator.road_network(), 0, 100),
+            Err(RenderError::InvalidSize { .. })
+        ));
+        let renderer = FrameRenderer::new(simulator.road_network(), 200, 100).unwrap();
+
+        // The network is 120 m wide with the margins, so 1 m is 5/3 px
+        let frame = renderer.render(&simulator);
+        let light = frame.pixel(183, 50).unwrap();
+        assert!(light.red() > 200 && light.green() < 50);
+        let car = frame.pixel(17, 50).unwrap();
+        assert!(car.blue() > 150 && car.red() < 100);
+        let grass = frame.pixel(100, 10).unwrap();
+        assert!(grass.green() > 200);
+
+        assert!(matches!(
+            FrameRenderer::new(simulator.road_network(), u32::MAX, 1),
+            Err(RenderError::InvalidSize { .. })
+        ));
+
+        let directory = std::env::temp_dir().join(format!(
+            "traffic-simulator-frames-{}-{}",
+            std::process::id(),
+            std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .unwrap()
+                .as_nanos()
+        ));
+        assert!(matches!(
+            renderer.render_sequence(&mut simulator, &[1.0], 0.0, &directory),
+            Err(RenderError::InvalidTimeStep(_))
+        ));
+        assert!(matches!(
+            renderer.render_sequence(&mut simulator, &[1.0, f32::INFINITY], 0.1, &directory),
+            Err(RenderError::InvalidFrameTime(_))
+        ));
+        let frames = renderer
+            .render_sequence(&mut simulator, &[0.0, 1.0, 2.0], 0.1, &directory)
+            .unwrap();
+        assert_eq!(frames.len(), 3);
+        assert!((simulator.current_time() - 2.0).abs() < 1e-3);
+        let png = std::fs::read(&frames[2]).unwrap();
+        assert_eq!(&png[1..4], b"PNG");
+        std::fs::remove_dir_all(directory).unwrap();
+    }
+
+    #[test]
+    fn snapshot_continues_like_the_original() {
+        let mut simulator = Simulator::new(
+            RoadNetwork::new(
+                (0..3)
+                    .map(|id| {
+                        (
+                            id,
+                            Node::new(
+                                id,
+                                Point3::new(id as f32 * 100.0, 0.0, 0.0),
+                                50.0 / 3.6,
+                                if id < 2 { vec![id + 1] } else { Vec::new() },
+                                None,
+                                None,
+                            ),
+                        )
+                    })
+                    .collect(),
+            ),
+            vec![Box::new(TimedTrafficLight::new(
+                1,
+                vec![
+                    (5.0, TrafficLightState::Red),
+                    (5.0, TrafficLightState::Green),
+                ],
+            ))],
+        );
+        simulator.add_demand(TrafficDemand::new(0, 0, 2, 1800.0));
+        simulator.record_trajectories(1.0);
+        for _ in 0..30 {
+            simulator.tick(0.1);
+        }
+
+        let snapshot = simulator.snapshot();
+        let points_before = simulator.trajectories().unwrap().points().to_vec();
+        let emissions_before = simulator.emissions().total();
+        let mut original = simulator.clone();
+        for _ in 0..100 {
+            original.tick(0.1);
+        }
+
+        for _ in 0..50 {
+            simulator.tick(0.1);
+        }
+        simulator.restore(&snapshot);
+        assert_eq!(simulator.current_time(), snapshot.current_time());
+        assert_eq!(simulator.trajectories().unwrap().points(), points_before);
+        assert_eq!(simulator.emissions().total(), emissions_before);
+        for _ in 0..100 {
+            simulator.tick(0.1);
+        }
+
+        assert!(!original.current_road_users().is_empty());
+        assert_eq!(original.current_time(), simulator.current_time());
+        assert_eq!(
+            original
+                .current_road_users()
+                .iter()
+                .map(|user| (user.id, user.location()))
+                .collect::<Vec<_>>(),
+            simulator
+                .current_road_users()
+                .iter()
+                .map(|user| (user.id, user.location()))
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(
+            original.traffic_lights()[0].get_state(),
+            simulator.traffic_lights()[0].get_state()
+        );
+        assert_eq!(
+            original.trajectories().unwrap().points(),
+            simulator.trajectories().unwrap().points()
+        );
+    }
 }
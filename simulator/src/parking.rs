@@ -0,0 +1,77 @@
+use crate::user::RoadUser;
+
+/// A place at a node where vehicles can leave the network for a while
+#[derive(Debug, Clone)]
+pub struct ParkingFacility {
+    id: u32,
+    node: u32,
+    capacity: u32,
+    occupied: u32,
+}
+
+impl ParkingFacility {
+    pub fn new(id: u32, node: u32, capacity: u32) -> Self {
+        Self {
+            id,
+            node,
+            capacity,
+            occupied: 0,
+        }
+    }
+
+    pub fn id(&self) -> u32 {
+        self.id
+    }
+
+    pub fn node(&self) -> u32 {
+        self.node
+    }
+
+    pub fn capacity(&self) -> u32 {
+        self.capacity
+    }
+
+    pub fn occupied(&self) -> u32 {
+        self.occupied
+    }
+
+    pub fn free_spaces(&self) -> u32 {
+        self.capacity.saturating_sub(self.occupied)
+    }
+
+    pub fn is_full(&self) -> bool {
+        self.free_spaces() == 0
+    }
+
+    pub(crate) fn enter(&mut self) {
+        self.occupied += 1;
+    }
+
+    pub(crate) fn leave(&mut self) {
+        self.occupied = self.occupied.saturating_sub(1);
+    }
+}
+
+/// One trip of a trip chain: drive to the destination and stay parked there for a while
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TripLeg {
+    pub destination: u32,
+    pub parking_duration: f32, // s
+}
+
+impl TripLeg {
+    pub fn new(destination: u32, parking_duration: f32) -> Self {
+        Self {
+            destination,
+            parking_duration,
+        }
+    }
+}
+
+/// A road user that is temporarily out of the network
+#[derive(Debug, Clone)]
+pub(crate) struct ParkedVehicle {
+    pub user: RoadUser,
+    pub facility: u32,
+    pub leaves_at: f32,
+}
@@ -0,0 +1,65 @@
+use std::collections::VecDeque;
+
+use crate::traffic_light::TrafficLightState;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct Leader {
+    pub gap: f32,   // m
+    pub speed: f32, // m/s
+}
+
+/// What a driver saw at a moment in time
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Observation {
+    pub time: f32,
+    pub own_speed: f32,
+    pub leader: Option<Leader>,
+    /// The lights on the path of the driver with their state
+    pub traffic_lights: Vec<(u32, TrafficLightState)>,
+    /// The speed limit of the node the driver is heading to
+    pub speed_limit: f32,
+}
+
+impl Observation {
+    pub fn traffic_light_state(&self, node: u32) -> Option<TrafficLightState> {
+        self.traffic_lights
+            .iter()
+            .find_map(|(light_node, state)| (*light_node == node).then_some(*state))
+    }
+
+    /// The leader as it is expected to be at the given time, assuming both we and the
+    /// leader kept the speed we had when this observation was made
+    pub fn extrapolated_leader(&self, time: f32) -> Option<Leader> {
+        let elapsed = time - self.time;
+
+        self.leader.map(|leader| Leader {
+            gap: leader.gap + (leader.speed - self.own_speed) * elapsed,
+            speed: leader.speed,
+        })
+    }
+}
+
+/// Remembers what a driver observed so it can react to it after its reaction time
+#[derive(Debug, Clone, Default)]
+pub(crate) struct PerceptionBuffer {
+    observations: VecDeque<Observation>,
+}
+
+impl PerceptionBuffer {
+    /// Stores the new observation and returns the one the driver is acting on now
+    pub fn observe(&mut self, observation: Observation, reaction_time: f32) -> &Observation {
+        let perceived_time = observation.time - reaction_time;
+        self.observations.push_back(observation);
+
+        // Only the newest observation that's older than the reaction time is still relevant
+        while self
+            .observations
+            .get(1)
+            .is_some_and(|next| next.time <= perceived_time)
+        {
+            self.observations.pop_front();
+        }
+
+        self.observations.front().unwrap()
+    }
+}
@@ -0,0 +1,266 @@
+use std::fmt::Debug;
+
+use nalgebra::Point3;
+use serde::{Deserialize, Serialize};
+
+const SEMI_MAJOR_AXIS: f64 = 6_378_137.0; // m, WGS84
+const FLATTENING: f64 = 1.0 / 298.257_223_563; // WGS84
+const ECCENTRICITY_SQUARED: f64 = FLATTENING * (2.0 - FLATTENING);
+
+const UTM_SCALE_FACTOR: f64 = 0.9996;
+const UTM_FALSE_EASTING: f64 = 500_000.0; // m
+const UTM_FALSE_NORTHING_SOUTH: f64 = 10_000_000.0; // m
+
+/// A position on the WGS84 ellipsoid
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct GeoPoint {
+    pub latitude: f64,  // degrees
+    pub longitude: f64, // degrees
+    pub altitude: f64,  // m
+}
+
+impl GeoPoint {
+    pub fn new(latitude: f64, longitude: f64, altitude: f64) -> Self {
+        Self {
+            latitude,
+            longitude,
+            altitude,
+        }
+    }
+}
+
+/// Converts between the local coordinates of the network (m, x east, y north) and WGS84
+pub trait Projection: Debug {
+    fn to_geographic(&self, location: Point3<f32>) -> GeoPoint;
+    fn to_local(&self, point: GeoPoint) -> Point3<f32>;
+}
+
+/// Leaves the coordinates as they are, for networks without a geographic reference.
+/// The latitude is the y and the longitude the x coordinate.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct LocalCoordinates;
+
+impl Projection for LocalCoordinates {
+    fn to_geographic(&self, location: Point3<f32>) -> GeoPoint {
+        GeoPoint::new(location.y as f64, location.x as f64, location.z as f64)
+    }
+
+    fn to_local(&self, point: GeoPoint) -> Point3<f32> {
+        Point3::new(
+            point.longitude as f32,
+            point.latitude as f32,
+            point.altitude as f32,
+        )
+    }
+}
+
+/// Treats the earth as flat around the origin. Good enough for networks of a few kilometres.
+/// Road networks keep a `TransverseMercator` as their coordinate reference, which is accurate
+/// further out; this is a cheaper stand-alone projection, e.g. for exporting a network that
+/// isn't georeferenced with `geojson::network_features` at a chosen origin.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Equirectangular {
+    origin: GeoPoint,
+}
+
+impl Equirectangular {
+    /// The origin is where the local point (0, 0, 0) is
+    pub fn new(origin: GeoPoint) -> Self {
+        Self { origin }
+    }
+
+    pub fn origin(&self) -> GeoPoint {
+        self.origin
+    }
+}
+
+impl Projection for Equirectangular {
+    fn to_geographic(&self, location: Point3<f32>) -> GeoPoint {
+        let latitude = self.origin.latitude + (location.y as f64 / SEMI_MAJOR_AXIS).to_degrees();
+        let longitude = self.origin.longitude
+            + (location.x as f64 / (SEMI_MAJOR_AXIS * self.origin.latitude.to_radians().cos()))
+                .to_degrees();
+
+        GeoPoint::new(
+            latitude,
+            longitude,
+            self.origin.altitude + location.z as f64,
+        )
+    }
+
+    fn to_local(&self, point: GeoPoint) -> Point3<f32> {
+        let y = (point.latitude - self.origin.latitude).to_radians() * SEMI_MAJOR_AXIS;
+        let x = (point.longitude - self.origin.longitude).to_radians()
+            * SEMI_MAJOR_AXIS
+            * self.origin.latitude.to_radians().cos();
+
+        Point3::new(
+            x as f32,
+            y as f32,
+            (point.altitude - self.origin.altitude) as f32,
+        )
+    }
+}
+
+/// The transverse Mercator projection on the WGS84 ellipsoid, following Snyder's series.
+/// It's accurate to well below a metre within a few degrees of the central meridian, which
+/// covers a UTM zone.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct TransverseMercator {
+    central_meridian: f64,   // degrees
+    latitude_of_origin: f64, // degrees
+    scale_factor: f64,
+    false_easting: f64,  // m
+    false_northing: f64, // m
+}
+
+impl TransverseMercator {
+    pub fn new(
+        central_meridian: f64,
+        latitude_of_origin: f64,
+        scale_factor: f64,
+        false_easting: f64,
+        false_northing: f64,
+    ) -> Self {
+        Self {
+            central_meridian,
+            latitude_of_origin,
+            scale_factor,
+            false_easting,
+            false_northing,
+        }
+    }
+
+    /// A projection centred on the origin, which ends up at the local point (0, 0, 0)
+    pub fn local(origin: GeoPoint) -> Self {
+        Self::new(origin.longitude, origin.latitude, 1.0, 0.0, 0.0)
+    }
+
+    /// Zones are numbered 1 to 60 from 180° west
+    pub fn utm(zone: u8, northern_hemisphere: bool) -> Self {
+        Self::new(
+            zone as f64 * 6.0 - 183.0,
+            0.0,
+            UTM_SCALE_FACTOR,
+            UTM_FALSE_EASTING,
+            if northern_hemisphere {
+                0.0
+            } else {
+                UTM_FALSE_NORTHING_SOUTH
+            },
+        )
+    }
+
+    pub fn utm_zone(longitude: f64) -> u8 {
+        (((longitude + 180.0) / 6.0).floor() as i32).rem_euclid(60) as u8 + 1
+    }
+
+    /// Moves the local coordinates by the offset, like the `netOffset` of SUMO networks.
+    /// With UTM this keeps the coordinates small enough to be precise in an `f32`.
+    pub fn with_offset(mut self, x: f64, y: f64) -> Self {
+        self.false_easting += x;
+        self.false_northing += y;
+        self
+    }
+
+    /// `(x, y)` in m for a latitude and longitude in degrees
+    pub fn project(&self, latitude: f64, longitude: f64) -> (f64, f64) {
+        let k0 = self.scale_factor;
+        let e2 = ECCENTRICITY_SQUARED;
+        let ep2 = e2 / (1.0 - e2);
+
+        let phi = latitude.to_radians();
+        let (sin_phi, cos_phi, tan_phi) = (phi.sin(), phi.cos(), phi.tan());
+
+        let n = SEMI_MAJOR_AXIS / (1.0 - e2 * sin_phi.powi(2)).sqrt();
+        let t = tan_phi.powi(2);
+        let c = ep2 * cos_phi.powi(2);
+        let a = (longitude - self.central_meridian).to_radians() * cos_phi;
+
+        let x = k0
+            * n
+            * (a + (1.0 - t + c) * a.powi(3) / 6.0
+                + (5.0 - 18.0 * t + t.powi(2) + 72.0 * c - 58.0 * ep2) * a.powi(5) / 120.0);
+        let y = k0
+            * (meridian_arc(phi) - meridian_arc(self.latitude_of_origin.to_radians())
+                + n * tan_phi
+                    * (a.powi(2) / 2.0
+                        + (5.0 - t + 9.0 * c + 4.0 * c.powi(2)) * a.powi(4) / 24.0
+                        + (61.0 - 58.0 * t + t.powi(2) + 600.0 * c - 330.0 * ep2) * a.powi(6)
+                            / 720.0));
+
+        (x + self.false_easting, y + self.false_northing)
+    }
+
+    /// `(latitude, longitude)` in degrees for a projected point in m
+    pub fn unproject(&self, x: f64, y: f64) -> (f64, f64) {
+        let k0 = self.scale_factor;
+        let e2 = ECCENTRICITY_SQUARED;
+        let ep2 = e2 / (1.0 - e2);
+        let (x, y) = (x - self.false_easting, y - self.false_northing);
+
+        let m = meridian_arc(self.latitude_of_origin.to_radians()) + y / k0;
+        let mu = m
+            / (SEMI_MAJOR_AXIS
+                * (1.0 - e2 / 4.0 - 3.0 * e2.powi(2) / 64.0 - 5.0 * e2.powi(3) / 256.0));
+        let e1 = (1.0 - (1.0 - e2).sqrt()) / (1.0 + (1.0 - e2).sqrt());
+
+        let phi1 = mu
+            + (3.0 * e1 / 2.0 - 27.0 * e1.powi(3) / 32.0) * (2.0 * mu).sin()
+            + (21.0 * e1.powi(2) / 16.0 - 55.0 * e1.powi(4) / 32.0) * (4.0 * mu).sin()
+            + (151.0 * e1.powi(3) / 96.0) * (6.0 * mu).sin()
+            + (1097.0 * e1.powi(4) / 512.0) * (8.0 * mu).sin();
+        let (sin_phi1, cos_phi1, tan_phi1) = (phi1.sin(), phi1.cos(), phi1.tan());
+
+        let c1 = ep2 * cos_phi1.powi(2);
+        let t1 = tan_phi1.powi(2);
+        let n1 = SEMI_MAJOR_AXIS / (1.0 - e2 * sin_phi1.powi(2)).sqrt();
+        let r1 = SEMI_MAJOR_AXIS * (1.0 - e2) / (1.0 - e2 * sin_phi1.powi(2)).powf(1.5);
+        let d = x / (n1 * k0);
+
+        let latitude = phi1
+            - (n1 * tan_phi1 / r1)
+                * (d.powi(2) / 2.0
+                    - (5.0 + 3.0 * t1 + 10.0 * c1 - 4.0 * c1.powi(2) - 9.0 * ep2) * d.powi(4)
+                        / 24.0
+                    + (61.0 + 90.0 * t1 + 298.0 * c1 + 45.0 * t1.powi(2)
+                        - 252.0 * ep2
+                        - 3.0 * c1.powi(2))
+                        * d.powi(6)
+                        / 720.0);
+        let longitude = (d - (1.0 + 2.0 * t1 + c1) * d.powi(3) / 6.0
+            + (5.0 - 2.0 * c1 + 28.0 * t1 - 3.0 * c1.powi(2) + 8.0 * ep2 + 24.0 * t1.powi(2))
+                * d.powi(5)
+                / 120.0)
+            / cos_phi1;
+
+        (
+            latitude.to_degrees(),
+            self.central_meridian + longitude.to_degrees(),
+        )
+    }
+}
+
+impl Projection for TransverseMercator {
+    fn to_geographic(&self, location: Point3<f32>) -> GeoPoint {
+        let (latitude, longitude) = self.unproject(location.x as f64, location.y as f64);
+        GeoPoint::new(latitude, longitude, location.z as f64)
+    }
+
+    fn to_local(&self, point: GeoPoint) -> Point3<f32> {
+        let (x, y) = self.project(point.latitude, point.longitude);
+        Point3::new(x as f32, y as f32, point.altitude as f32)
+    }
+}
+
+/// The distance along the meridian from the equator to the latitude (radians)
+fn meridian_arc(phi: f64) -> f64 {
+    let e2 = ECCENTRICITY_SQUARED;
+    let (e4, e6) = (e2.powi(2), e2.powi(3));
+
+    SEMI_MAJOR_AXIS
+        * ((1.0 - e2 / 4.0 - 3.0 * e4 / 64.0 - 5.0 * e6 / 256.0) * phi
+            - (3.0 * e2 / 8.0 + 3.0 * e4 / 32.0 + 45.0 * e6 / 1024.0) * (2.0 * phi).sin()
+            + (15.0 * e4 / 256.0 + 45.0 * e6 / 1024.0) * (4.0 * phi).sin()
+            - (35.0 * e6 / 3072.0) * (6.0 * phi).sin())
+}
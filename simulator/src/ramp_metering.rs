@@ -0,0 +1,162 @@
+use crate::{
+    detector::{self, Detector},
+    traffic_light::{TrafficLight, TrafficLightState},
+};
+
+/// Shows green for a short moment every cycle so one vehicle at a time is released
+#[derive(Debug, Clone)]
+struct MeteringCycle {
+    green_time: f32, // s
+    cycle_start: f32,
+    state: TrafficLightState,
+}
+
+impl MeteringCycle {
+    fn new(green_time: f32) -> Self {
+        Self {
+            green_time,
+            cycle_start: 0.0,
+            state: TrafficLightState::Red,
+        }
+    }
+
+    /// The rate is in vehicles per hour
+    fn tick(&mut self, current_time: f32, rate: f32) {
+        let cycle_time = (3600.0 / rate.max(f32::EPSILON)).max(self.green_time);
+
+        if current_time >= self.cycle_start + cycle_time {
+            self.cycle_start = current_time;
+        }
+
+        self.state = if current_time - self.cycle_start < self.green_time {
+            TrafficLightState::Green
+        } else {
+            TrafficLightState::Red
+        };
+    }
+}
+
+/// A ramp meter that releases vehicles at a fixed rate
+#[derive(Debug, Clone)]
+pub struct FixedRateRampMeter {
+    node: u32,
+    rate: f32, // vehicles per hour
+    cycle: MeteringCycle,
+}
+
+impl FixedRateRampMeter {
+    /// The light is green for `green_time` seconds every cycle
+    pub fn new(node: u32, rate: f32, green_time: f32) -> Self {
+        Self {
+            node,
+            rate,
+            cycle: MeteringCycle::new(green_time),
+        }
+    }
+
+    /// Vehicles per hour
+    pub fn rate(&self) -> f32 {
+        self.rate
+    }
+}
+
+impl TrafficLight for FixedRateRampMeter {
+    fn node(&self) -> u32 {
+        self.node
+    }
+
+    fn tick(&mut self, current_time: f32) {
+        self.cycle.tick(current_time, self.rate);
+    }
+
+    fn get_state(&self) -> TrafficLightState {
+        self.cycle.state
+    }
+}
+
+/// A ramp meter with the ALINEA feedback law. The release rate is adjusted to keep the
+/// occupancy measured on the mainline downstream of the ramp at a target value:
+/// `r(k) = r(k - 1) + K_R * (target occupancy - measured occupancy)`
+#[derive(Debug, Clone)]
+pub struct AlineaRampMeter {
+    node: u32,
+    detectors: Vec<u32>,
+    target_occupancy: f32,
+    gain: f32,            // vehicles per hour per unit of occupancy
+    min_rate: f32,        // vehicles per hour
+    max_rate: f32,        // vehicles per hour
+    update_interval: f32, // s
+
+    rate: f32,
+    measured_occupancy: f32,
+    last_update: f32,
+    cycle: MeteringCycle,
+}
+
+impl AlineaRampMeter {
+    /// The occupancy is the mean of the given mainline detectors. It is a fraction between
+    /// 0 and 1, so a typical gain of 70 veh/h per percent becomes 7000.
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        node: u32,
+        detectors: Vec<u32>,
+        target_occupancy: f32,
+        gain: f32,
+        min_rate: f32,
+        max_rate: f32,
+        update_interval: f32,
+        green_time: f32,
+    ) -> Self {
+        Self {
+            node,
+            detectors,
+            target_occupancy,
+            gain,
+            min_rate,
+            max_rate,
+            update_interval,
+            rate: max_rate,
+            measured_occupancy: 0.0,
+            last_update: 0.0,
+            cycle: MeteringCycle::new(green_time),
+        }
+    }
+
+    /// Vehicles per hour
+    pub fn rate(&self) -> f32 {
+        self.rate
+    }
+}
+
+impl TrafficLight for AlineaRampMeter {
+    fn node(&self) -> u32 {
+        self.node
+    }
+
+    fn observe_detectors(&mut self, detectors: &[Detector]) {
+        let occupancies = self
+            .detectors
+            .iter()
+            .filter_map(|id| detector::find_detector(detectors, *id))
+            .map(Detector::occupancy)
+            .collect::<Vec<_>>();
+
+        if !occupancies.is_empty() {
+            self.measured_occupancy = occupancies.iter().sum::<f32>() / occupancies.len() as f32;
+        }
+    }
+
+    fn tick(&mut self, current_time: f32) {
+        if current_time - self.last_update >= self.update_interval {
+            self.rate = (self.rate + self.gain * (self.target_occupancy - self.measured_occupancy))
+                .clamp(self.min_rate, self.max_rate);
+            self.last_update = current_time;
+        }
+
+        self.cycle.tick(current_time, self.rate);
+    }
+
+    fn get_state(&self) -> TrafficLightState {
+        self.cycle.state
+    }
+}
@@ -0,0 +1,230 @@
+use std::{
+    fmt::Display,
+    path::{Path, PathBuf},
+};
+
+use tiny_skia::{Color, FillRule, LineCap, Paint, PathBuilder, Pixmap, Stroke, Transform};
+
+use crate::{
+    collision::Footprint, road::RoadNetwork, traffic_light::TrafficLightState,
+    vehicle::VehicleClass, Simulator, TIME_EPSILON,
+};
+
+const MARGIN: f32 = 10.0; // m
+const ROAD_WIDTH: f32 = 2.8; // m
+const LIGHT_RADIUS: f32 = 1.5; // m
+
+#[derive(Debug)]
+pub enum RenderError {
+    Io(std::io::Error),
+    /// The frame has no pixels or is too large to draw
+    InvalidSize {
+        width: u32,
+        height: u32,
+    },
+    Encoding(String),
+    /// The time step must be positive to reach the times of the frames
+    InvalidTimeStep(f32),
+    /// The time of a frame must be finite to be reached
+    InvalidFrameTime(f32),
+}
+
+impl Display for RenderError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RenderError::Io(error) => write!(f, "could not write the frame: {error}"),
+            RenderError::InvalidSize { width, height } => {
+                write!(f, "{width}x{height} is not a valid frame size")
+            }
+            RenderError::Encoding(error) => write!(f, "could not encode the frame: {error}"),
+            RenderError::InvalidTimeStep(delta_time) => {
+                write!(f, "{delta_time} s is not a valid time step")
+            }
+            RenderError::InvalidFrameTime(time) => {
+                write!(f, "{time} s is not a valid time for a frame")
+            }
+        }
+    }
+}
+
+impl std::error::Error for RenderError {}
+
+impl From<std::io::Error> for RenderError {
+    fn from(error: std::io::Error) -> Self {
+        RenderError::Io(error)
+    }
+}
+
+/// Draws the simulation from above on the CPU: the roads, the traffic lights in the colour
+/// of their state and the road users as rectangles of their size.
+/// The view is fitted to the network once, so all frames line up.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FrameRenderer {
+    width: u32,  // px
+    height: u32, // px
+    transform: Transform,
+}
+
+impl FrameRenderer {
+    pub fn new(network: &RoadNetwork, width: u32, height: u32) -> Result<Self, RenderError> {
+        // The limits of `Pixmap::new`, so rendering a frame can't fail
+        let bytes = u64::from(width) * u64::from(height) * 4;
+        if width == 0 || height == 0 || width > i32::MAX as u32 / 4 || bytes > isize::MAX as u64 {
+            return Err(RenderError::InvalidSize { width, height });
+        }
+
+        let (min, max) = network.bounds();
+
+        // The largest scale at which the network fits, centred in the frame.
+        // The y axis of images points down.
+        let (size_x, size_y) = (max.x - min.x + 2.0 * MARGIN, max.y - min.y + 2.0 * MARGIN);
+        let scale = (width as f32 / size_x).min(height as f32 / size_y); // px/m
+        let center = (min + max.coords) / 2.0;
+
+        Ok(Self {
+            width,
+            height,
+            transform: Transform::from_row(
+                scale,
+                0.0,
+                0.0,
+                -scale,
+                width as f32 / 2.0 - center.x * scale,
+                height as f32 / 2.0 + center.y * scale,
+            ),
+        })
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    pub fn render(&self, simulator: &Simulator) -> Pixmap {
+        let mut pixmap =
+            Pixmap::new(self.width, self.height).expect("the size is checked by the constructor");
+        pixmap.fill(Color::from_rgba8(235, 240, 230, 255));
+
+        let network = simulator.road_network();
+        let mut roads = PathBuilder::new();
+        for id in network.all_node_ids() {
+            let node = network.find_node(id);
+            for next in node.next_node_ids() {
+                let next = network.find_node(*next);
+                roads.move_to(node.location().x, node.location().y);
+                roads.line_to(next.location().x, next.location().y);
+            }
+        }
+        if let Some(roads) = roads.finish() {
+            pixmap.stroke_path(
+                &roads,
+                &paint(Color::from_rgba8(90, 90, 90, 255)),
+                &Stroke {
+                    width: ROAD_WIDTH,
+                    line_cap: LineCap::Round,
+                    ..Default::default()
+                },
+                self.transform,
+                None,
+            );
+        }
+
+        for light in simulator
+            .traffic_lights()
+            .iter()
+            .filter(|light| network.contains_node(light.node()))
+        {
+            let location = network.find_node(light.node()).location();
+            let colour = match light.get_state() {
+                TrafficLightState::Red => Color::from_rgba8(220, 30, 30, 255),
+                TrafficLightState::Orange => Color::from_rgba8(240, 150, 0, 255),
+                TrafficLightState::Green => Color::from_rgba8(30, 180, 50, 255),
+            };
+            if let Some(circle) = PathBuilder::from_circle(location.x, location.y, LIGHT_RADIUS) {
+                pixmap.fill_path(
+                    &circle,
+                    &paint(colour),
+                    FillRule::Winding,
+                    self.transform,
+                    None,
+                );
+            }
+        }
+
+        for user in simulator.current_road_users() {
+            let corners = Footprint::of(user).corners();
+            let mut outline = PathBuilder::new();
+            outline.move_to(corners[0].x, corners[0].y);
+            for corner in corners[1..].iter() {
+                outline.line_to(corner.x, corner.y);
+            }
+            outline.close();
+
+            let colour = match user.class() {
+                VehicleClass::PassengerCar => Color::from_rgba8(40, 90, 200, 255),
+                VehicleClass::Bus => Color::from_rgba8(230, 180, 0, 255),
+                VehicleClass::Truck => Color::from_rgba8(120, 60, 160, 255),
+                VehicleClass::ElectricCar => Color::from_rgba8(0, 170, 170, 255),
+            };
+            if let Some(outline) = outline.finish() {
+                pixmap.fill_path(
+                    &outline,
+                    &paint(colour),
+                    FillRule::Winding,
+                    self.transform,
+                    None,
+                );
+            }
+        }
+
+        pixmap
+    }
+
+    pub fn render_png(&self, simulator: &Simulator) -> Result<Vec<u8>, RenderError> {
+        self.render(simulator)
+            .encode_png()
+            .map_err(|error| RenderError::Encoding(error.to_string()))
+    }
+
+    /// Runs the simulator with the time step and writes a frame when each of the times is
+    /// reached, as `frame_00000.png`, `frame_00001.png`, ... in the directory.
+    /// Returns the paths of the frames. The time step must be positive and the times finite.
+    pub fn render_sequence(
+        &self,
+        simulator: &mut Simulator,
+        times: &[f32],
+        delta_time: f32,
+        directory: impl AsRef<Path>,
+    ) -> Result<Vec<PathBuf>, RenderError> {
+        if delta_time <= 0.0 || !delta_time.is_finite() {
+            return Err(RenderError::InvalidTimeStep(delta_time));
+        }
+        if let Some(time) = times.iter().find(|time| !time.is_finite()) {
+            return Err(RenderError::InvalidFrameTime(*time));
+        }
+        std::fs::create_dir_all(directory.as_ref())?;
+
+        let mut frames = Vec::new();
+        for (index, time) in times.iter().enumerate() {
+            while simulator.current_time() + TIME_EPSILON < *time {
+                simulator.tick(delta_time);
+            }
+
+            let path = directory.as_ref().join(format!("frame_{index:05}.png"));
+            std::fs::write(&path, self.render_png(simulator)?)?;
+            frames.push(path);
+        }
+
+        Ok(frames)
+    }
+}
+
+fn paint(colour: Color) -> Paint<'static> {
+    let mut paint = Paint::default();
+    paint.set_color(colour);
+    paint.anti_alias = true;
+    paint
+}
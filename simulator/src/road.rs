@@ -2,22 +2,87 @@ use nalgebra::{Point3, Vector3};
 use ordered_float::OrderedFloat;
 use std::{collections::HashMap, hash::Hash};
 
+use crate::projection::{GeoPoint, LocalCoordinates, Projection, TransverseMercator};
+
+#[derive(Clone)]
 pub struct RoadNetwork {
     nodes: HashMap<u32, Node>,
+    /// Ties the local coordinates of the nodes to the real world. This is a transverse
+    /// Mercator projection because georeferenced SUMO networks use UTM, and it stays accurate
+    /// over a whole zone.
+    coordinate_reference: Option<TransverseMercator>,
 }
 
 impl RoadNetwork {
     pub fn new(nodes: HashMap<u32, Node>) -> Self {
-        Self { nodes }
+        Self {
+            nodes,
+            coordinate_reference: None,
+        }
+    }
+
+    pub fn with_coordinate_reference(mut self, reference: TransverseMercator) -> Self {
+        self.coordinate_reference = Some(reference);
+        self
+    }
+
+    pub fn coordinate_reference(&self) -> Option<&TransverseMercator> {
+        self.coordinate_reference.as_ref()
+    }
+
+    pub fn set_coordinate_reference(&mut self, reference: Option<TransverseMercator>) {
+        self.coordinate_reference = reference;
+    }
+
+    /// The coordinate reference, or the local coordinates as they are if there's none
+    pub fn projection(&self) -> &dyn Projection {
+        match &self.coordinate_reference {
+            Some(reference) => reference,
+            None => &LocalCoordinates,
+        }
+    }
+
+    pub fn to_geographic(&self, location: Point3<f32>) -> GeoPoint {
+        self.projection().to_geographic(location)
+    }
+
+    pub fn to_local(&self, point: GeoPoint) -> Point3<f32> {
+        self.projection().to_local(point)
     }
 
     pub fn find_node(&self, id: u32) -> &Node {
         self.nodes.get(&id).unwrap()
     }
 
+    pub fn find_node_mut(&mut self, id: u32) -> &mut Node {
+        self.nodes.get_mut(&id).unwrap()
+    }
+
+    pub fn contains_node(&self, id: u32) -> bool {
+        self.nodes.contains_key(&id)
+    }
+
     pub fn all_node_ids(&self) -> impl Iterator<Item = u32> + '_ {
         self.nodes.keys().copied()
     }
+
+    /// The lowest and highest corner of the box around all nodes. Both are the origin if
+    /// there are no nodes.
+    pub fn bounds(&self) -> (Point3<f32>, Point3<f32>) {
+        self.nodes
+            .values()
+            .map(Node::location)
+            .fold(
+                None,
+                |bounds: Option<(Point3<f32>, Point3<f32>)>, location| {
+                    Some(match bounds {
+                        Some((min, max)) => (min.inf(&location), max.sup(&location)),
+                        None => (location, location),
+                    })
+                },
+            )
+            .unwrap_or((Point3::origin(), Point3::origin()))
+    }
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
@@ -57,10 +122,20 @@ impl Node {
     pub fn next_nodes<'s, 'rn: 's>(
         &'s self,
         network: &'rn RoadNetwork,
-    ) -> impl Iterator<Item = &'rn Node> + '_ {
+    ) -> impl Iterator<Item = &'rn Node> + 's {
         self.next_nodes.iter().map(move |id| network.find_node(*id))
     }
 
+    pub fn next_node_ids(&self) -> &[u32] {
+        self.next_nodes.as_ref()
+    }
+
+    pub fn add_next_node(&mut self, id: u32) {
+        if !self.next_nodes.contains(&id) {
+            self.next_nodes.push(id);
+        }
+    }
+
     pub fn location(&self) -> Point3<f32> {
         Point3::new(self.location.x.0, self.location.y.0, self.location.z.0)
     }
@@ -69,6 +144,26 @@ impl Node {
         self.max_speed.0
     }
 
+    pub fn set_max_speed(&mut self, max_speed: f32) {
+        self.max_speed = max_speed.into();
+    }
+
+    pub fn adjacent_node_right_id(&self) -> Option<u32> {
+        self.adjacent_node_right
+    }
+
+    pub fn adjacent_node_left_id(&self) -> Option<u32> {
+        self.adjacent_node_left
+    }
+
+    pub fn set_adjacent_node_right(&mut self, id: Option<u32>) {
+        self.adjacent_node_right = id;
+    }
+
+    pub fn set_adjacent_node_left(&mut self, id: Option<u32>) {
+        self.adjacent_node_left = id;
+    }
+
     pub fn adjacent_node_right<'rn>(&self, network: &'rn RoadNetwork) -> Option<&'rn Node> {
         self.adjacent_node_right.map(|id| network.find_node(id))
     }
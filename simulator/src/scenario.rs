@@ -0,0 +1,279 @@
+use std::{collections::HashMap, fmt::Display, path::Path};
+
+use nalgebra::Point3;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    demand::TrafficDemand,
+    projection::TransverseMercator,
+    road::{Node, RoadNetwork},
+    traffic_light::{TimedTrafficLight, TrafficLight, TrafficLightState},
+    vehicle::VehicleClass,
+    Simulator,
+};
+
+/// Everything needed to set up a simulation, as stored in a scenario file
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Scenario {
+    pub nodes: Vec<NodeDefinition>,
+    #[serde(default)]
+    pub traffic_lights: Vec<TrafficLightDefinition>,
+    #[serde(default)]
+    pub demand: Vec<TrafficDemand>,
+    #[serde(default)]
+    pub timeline: Vec<ScheduledEvent>,
+    #[serde(default)]
+    pub seed: Option<u64>,
+    /// Where the local coordinates of the nodes are in the real world
+    #[serde(default)]
+    pub coordinate_reference: Option<TransverseMercator>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct NodeDefinition {
+    pub id: u32,
+    pub location: [f32; 3], // m
+    pub max_speed: f32,     // m/s
+    #[serde(default)]
+    pub next_nodes: Vec<u32>,
+    #[serde(default)]
+    pub adjacent_node_right: Option<u32>,
+    #[serde(default)]
+    pub adjacent_node_left: Option<u32>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TrafficLightDefinition {
+    pub node: u32,
+    /// `(duration in s, state)` pairs that repeat
+    pub schema: Vec<(f32, TrafficLightState)>,
+}
+
+/// An action that's executed once the simulation time passes the time of the event
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ScheduledEvent {
+    pub time: f32, // s
+    pub action: ScheduledAction,
+}
+
+impl ScheduledEvent {
+    pub fn new(time: f32, action: ScheduledAction) -> Self {
+        Self { time, action }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ScheduledAction {
+    /// Closes the node, for good or for the given number of seconds
+    CloseNode {
+        node: u32,
+        duration: Option<f32>,
+    },
+    /// Ends all incidents at the node
+    ReopenNode {
+        node: u32,
+    },
+    /// Replaces the traffic light at the node by a timed one with the schema
+    SetTrafficLightSchema {
+        node: u32,
+        schema: Vec<(f32, TrafficLightState)>,
+    },
+    /// Changes the max speed of the nodes in m/s
+    SetSpeedLimit {
+        nodes: Vec<u32>,
+        speed_limit: f32,
+    },
+    InjectVehicles {
+        vehicles: Vec<VehicleInjection>,
+    },
+    /// Changes the rate of a traffic demand in vehicles/h
+    SetDemandRate {
+        demand: u32,
+        rate: f32,
+    },
+}
+
+impl ScheduledAction {
+    /// The nodes the action refers to
+    pub fn nodes(&self) -> Vec<u32> {
+        match self {
+            ScheduledAction::CloseNode { node, .. }
+            | ScheduledAction::ReopenNode { node }
+            | ScheduledAction::SetTrafficLightSchema { node, .. } => vec![*node],
+            ScheduledAction::SetSpeedLimit { nodes, .. } => nodes.clone(),
+            ScheduledAction::InjectVehicles { vehicles } => vehicles
+                .iter()
+                .flat_map(|vehicle| [vehicle.origin, vehicle.destination])
+                .collect(),
+            ScheduledAction::SetDemandRate { .. } => Vec::new(),
+        }
+    }
+}
+
+/// A vehicle that departs standing still from the origin
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct VehicleInjection {
+    pub origin: u32,
+    pub destination: u32,
+    #[serde(default)]
+    pub class: VehicleClass,
+}
+
+#[derive(Debug)]
+pub enum ScenarioError {
+    Io(std::io::Error),
+    Parse(serde_json::Error),
+    /// A node is referenced that isn't defined
+    UnknownNode(u32),
+    /// A traffic light has an empty schema
+    EmptySchema {
+        node: u32,
+    },
+}
+
+impl Display for ScenarioError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ScenarioError::Io(error) => write!(f, "could not read the scenario: {error}"),
+            ScenarioError::Parse(error) => write!(f, "invalid scenario: {error}"),
+            ScenarioError::UnknownNode(node) => write!(f, "node {node} is not defined"),
+            ScenarioError::EmptySchema { node } => {
+                write!(f, "the traffic light at node {node} has an empty schema")
+            }
+        }
+    }
+}
+
+impl std::error::Error for ScenarioError {}
+
+impl From<std::io::Error> for ScenarioError {
+    fn from(error: std::io::Error) -> Self {
+        ScenarioError::Io(error)
+    }
+}
+
+impl From<serde_json::Error> for ScenarioError {
+    fn from(error: serde_json::Error) -> Self {
+        ScenarioError::Parse(error)
+    }
+}
+
+impl Scenario {
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, ScenarioError> {
+        Self::from_json(&std::fs::read_to_string(path)?)
+    }
+
+    pub fn from_json(json: &str) -> Result<Self, ScenarioError> {
+        let scenario: Self = serde_json::from_str(json)?;
+        scenario.validate()?;
+        Ok(scenario)
+    }
+
+    pub fn to_json(&self) -> Result<String, ScenarioError> {
+        Ok(serde_json::to_string_pretty(self)?)
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), ScenarioError> {
+        std::fs::write(path, self.to_json()?)?;
+        Ok(())
+    }
+
+    /// Checks that everything refers to nodes that exist
+    pub fn validate(&self) -> Result<(), ScenarioError> {
+        let is_defined = |node: u32| self.nodes.iter().any(|definition| definition.id == node);
+        let check = |node: u32| {
+            if is_defined(node) {
+                Ok(())
+            } else {
+                Err(ScenarioError::UnknownNode(node))
+            }
+        };
+
+        for node in self.nodes.iter() {
+            for next_node in node.next_nodes.iter() {
+                check(*next_node)?;
+            }
+            for adjacent_node in [node.adjacent_node_right, node.adjacent_node_left]
+                .into_iter()
+                .flatten()
+            {
+                check(adjacent_node)?;
+            }
+        }
+
+        for light in self.traffic_lights.iter() {
+            check(light.node)?;
+            if light.schema.is_empty() {
+                return Err(ScenarioError::EmptySchema { node: light.node });
+            }
+        }
+
+        for demand in self.demand.iter() {
+            check(demand.origin())?;
+            check(demand.destination())?;
+        }
+
+        for event in self.timeline.iter() {
+            for node in event.action.nodes() {
+                check(node)?;
+            }
+            if let ScheduledAction::SetTrafficLightSchema { node, schema } = &event.action {
+                if schema.is_empty() {
+                    return Err(ScenarioError::EmptySchema { node: *node });
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn road_network(&self) -> RoadNetwork {
+        let mut network = RoadNetwork::new(
+            self.nodes
+                .iter()
+                .map(|node| {
+                    (
+                        node.id,
+                        Node::new(
+                            node.id,
+                            Point3::from(node.location),
+                            node.max_speed,
+                            node.next_nodes.clone(),
+                            node.adjacent_node_right,
+                            node.adjacent_node_left,
+                        ),
+                    )
+                })
+                .collect::<HashMap<_, _>>(),
+        );
+        network.set_coordinate_reference(self.coordinate_reference);
+        network
+    }
+
+    pub fn traffic_lights(&self) -> Vec<Box<dyn TrafficLight>> {
+        self.traffic_lights
+            .iter()
+            .map(|light| {
+                Box::new(TimedTrafficLight::new(light.node, light.schema.clone()))
+                    as Box<dyn TrafficLight>
+            })
+            .collect()
+    }
+
+    pub fn into_simulator(self) -> Simulator {
+        let mut simulator = Simulator::new(self.road_network(), self.traffic_lights());
+
+        if let Some(seed) = self.seed {
+            simulator.set_seed(seed);
+        }
+        for demand in self.demand {
+            simulator.add_demand(demand);
+        }
+        for event in self.timeline {
+            simulator.schedule_event(event);
+        }
+
+        simulator
+    }
+}
@@ -0,0 +1,142 @@
+use std::fmt::Debug;
+
+use crate::detector::{self, Detector};
+
+/// Changes the speed limit of a set of nodes while the simulation runs
+pub trait SpeedLimitController: CloneSpeedLimitController + Debug + Send + Sync {
+    fn nodes(&self) -> &[u32];
+    fn tick(&mut self, current_time: f32, detectors: &[Detector]);
+    /// None when the regular max speed of the nodes applies
+    fn current_limit(&self) -> Option<f32>;
+}
+
+/// Implemented for every controller that is `Clone`, so boxed controllers can be cloned with
+/// the simulator
+pub trait CloneSpeedLimitController {
+    /// A copy of the controller in its current state
+    fn clone_box(&self) -> Box<dyn SpeedLimitController>;
+}
+
+impl<T: SpeedLimitController + Clone + 'static> CloneSpeedLimitController for T {
+    fn clone_box(&self) -> Box<dyn SpeedLimitController> {
+        Box::new(self.clone())
+    }
+}
+
+impl Clone for Box<dyn SpeedLimitController> {
+    fn clone(&self) -> Self {
+        self.clone_box()
+    }
+}
+
+/// Applies speed limits during fixed time windows, e.g. for a school zone
+#[derive(Debug, Clone)]
+pub struct ScheduledSpeedLimit {
+    nodes: Vec<u32>,
+    schedule: Vec<(f32, f32, f32)>, // (start, end, limit)
+    repeat_every: Option<f32>,
+    current_limit: Option<f32>,
+}
+
+impl ScheduledSpeedLimit {
+    /// The schedule consists of `(start, end, limit)` windows. When `repeat_every` is set,
+    /// the schedule is repeated with that period, e.g. every day.
+    pub fn new(nodes: Vec<u32>, schedule: Vec<(f32, f32, f32)>, repeat_every: Option<f32>) -> Self {
+        Self {
+            nodes,
+            schedule,
+            repeat_every,
+            current_limit: None,
+        }
+    }
+}
+
+impl SpeedLimitController for ScheduledSpeedLimit {
+    fn nodes(&self) -> &[u32] {
+        self.nodes.as_ref()
+    }
+
+    fn tick(&mut self, current_time: f32, _detectors: &[Detector]) {
+        let time_in_schedule = match self.repeat_every {
+            Some(period) => current_time % period,
+            None => current_time,
+        };
+
+        self.current_limit = self
+            .schedule
+            .iter()
+            .find(|(start, end, _)| (*start..*end).contains(&time_in_schedule))
+            .map(|(_, _, limit)| *limit);
+    }
+
+    fn current_limit(&self) -> Option<f32> {
+        self.current_limit
+    }
+}
+
+/// Lowers the speed limit when the occupancy of a detector goes up, like the variable speed
+/// limits above motorways
+#[derive(Debug, Clone)]
+pub struct OccupancySpeedLimit {
+    nodes: Vec<u32>,
+    detector: u32,
+    levels: Vec<(f32, f32)>, // (occupancy threshold, limit)
+    hold_time: f32,          // s
+    current_limit: Option<f32>,
+    last_change: f32,
+}
+
+impl OccupancySpeedLimit {
+    /// The levels are `(occupancy threshold, limit)` pairs. The limit of the highest threshold
+    /// that's exceeded is shown. A new limit is shown for at least `hold_time` seconds.
+    pub fn new(
+        nodes: Vec<u32>,
+        detector: u32,
+        mut levels: Vec<(f32, f32)>,
+        hold_time: f32,
+    ) -> Self {
+        levels.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+        Self {
+            nodes,
+            detector,
+            levels,
+            hold_time,
+            current_limit: None,
+            last_change: f32::NEG_INFINITY,
+        }
+    }
+}
+
+impl SpeedLimitController for OccupancySpeedLimit {
+    fn nodes(&self) -> &[u32] {
+        self.nodes.as_ref()
+    }
+
+    fn tick(&mut self, current_time: f32, detectors: &[Detector]) {
+        if current_time - self.last_change < self.hold_time {
+            return;
+        }
+
+        let Some(detector) = detector::find_detector(detectors, self.detector) else {
+            return;
+        };
+
+        let occupancy = detector.occupancy();
+        let new_limit = self
+            .levels
+            .iter()
+            .rev()
+            .find(|(threshold, _)| occupancy >= *threshold)
+            .map(|(_, limit)| *limit);
+
+        if new_limit != self.current_limit {
+            self.current_limit = new_limit;
+            self.last_change = current_time;
+        }
+    }
+
+    fn current_limit(&self) -> Option<f32> {
+        self.current_limit
+    }
+}
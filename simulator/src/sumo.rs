@@ -0,0 +1,918 @@
+use std::{collections::HashMap, fmt::Display, path::Path};
+
+use nalgebra::Point3;
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+
+use crate::{
+    driver::DriverBehaviour,
+    incident::RoadClosures,
+    projection::TransverseMercator,
+    road::{Node, RoadNetwork},
+    traffic_light::{TimedTrafficLight, TrafficLight, TrafficLightState},
+    user::RoadUser,
+    vehicle::VehicleClass,
+    Simulator,
+};
+
+/// Vehicle classes that never drive on the road network
+const NON_ROAD_CLASSES: &[&str] = &[
+    "pedestrian",
+    "bicycle",
+    "tram",
+    "rail_urban",
+    "rail",
+    "rail_electric",
+    "rail_fast",
+    "ship",
+];
+
+/// How far behind the end of a lane the traffic light of a signalised connection is placed
+const LINK_NODE_OFFSET: f32 = 1.0; // m
+
+/// Flows without an end stop after a day, like in SUMO
+const DEFAULT_FLOW_END: f32 = 86_400.0; // s
+
+#[derive(Debug)]
+pub enum SumoError {
+    Io(std::io::Error),
+    Xml(roxmltree::Error),
+    MissingAttribute {
+        element: String,
+        attribute: &'static str,
+    },
+    InvalidValue {
+        attribute: &'static str,
+        value: String,
+    },
+    UnknownEdge(String),
+    UnknownRoute(String),
+    UnknownVehicleType(String),
+    /// The edges of the route of the vehicle or flow aren't connected
+    DisconnectedRoute(String),
+}
+
+impl Display for SumoError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SumoError::Io(error) => write!(f, "could not read the file: {error}"),
+            SumoError::Xml(error) => write!(f, "invalid xml: {error}"),
+            SumoError::MissingAttribute { element, attribute } => {
+                write!(f, "<{element}> is missing the '{attribute}' attribute")
+            }
+            SumoError::InvalidValue { attribute, value } => {
+                write!(f, "'{value}' is not a valid value for '{attribute}'")
+            }
+            SumoError::UnknownEdge(edge) => write!(f, "edge '{edge}' is not in the network"),
+            SumoError::UnknownRoute(route) => write!(f, "route '{route}' is not defined"),
+            SumoError::UnknownVehicleType(vehicle_type) => {
+                write!(f, "vehicle type '{vehicle_type}' is not defined")
+            }
+            SumoError::DisconnectedRoute(id) => {
+                write!(f, "the route of '{id}' has edges that aren't connected")
+            }
+        }
+    }
+}
+
+impl std::error::Error for SumoError {}
+
+impl From<std::io::Error> for SumoError {
+    fn from(error: std::io::Error) -> Self {
+        SumoError::Io(error)
+    }
+}
+
+impl From<roxmltree::Error> for SumoError {
+    fn from(error: roxmltree::Error) -> Self {
+        SumoError::Xml(error)
+    }
+}
+
+/// A network converted from a SUMO `.net.xml` file.
+///
+/// Every lane becomes a chain of nodes along its shape, with the nodes of the neighbouring
+/// lanes of the same edge as adjacent nodes. Connections link the end of a lane straight to
+/// the start of the next one; the internal junction lanes aren't used. A signalised
+/// connection gets a node of its own just behind the end of the lane, so turns from the
+/// same lane can have their own light. Traffic light programs are converted with their
+/// phase durations, actuated programs run as if they're static. The `<location>` of a
+/// georeferenced network becomes the coordinate reference of the road network. A network
+/// with a projection that isn't supported is imported without one.
+pub struct SumoNetwork {
+    network: RoadNetwork,
+    traffic_lights: Vec<TimedTrafficLight>,
+    lane_nodes: HashMap<String, Vec<u32>>,
+    edge_lanes: HashMap<String, Vec<(u32, String)>>, // From the rightmost lane to the left
+    unsupported_projection: Option<String>,
+}
+
+impl SumoNetwork {
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, SumoError> {
+        Self::from_xml(&std::fs::read_to_string(path)?)
+    }
+
+    pub fn from_xml(xml: &str) -> Result<Self, SumoError> {
+        let document = roxmltree::Document::parse(xml)?;
+        let root = document.root_element();
+
+        let mut nodes = HashMap::new();
+        let mut lane_nodes = HashMap::new();
+        let mut edge_lanes = HashMap::new();
+        let mut next_id = 0;
+
+        for edge in root.children().filter(|node| node.has_tag_name("edge")) {
+            if edge
+                .attribute("function")
+                .is_some_and(|function| function != "normal")
+            {
+                continue;
+            }
+            let edge_id = required(edge, "id")?;
+
+            let mut lanes = edge
+                .children()
+                .filter(|node| node.has_tag_name("lane"))
+                .filter(|lane| is_road_lane(lane.attribute("allow")))
+                .map(|lane| {
+                    Ok((
+                        parse::<u32>(lane, "index")?,
+                        required(lane, "id")?.to_string(),
+                        parse::<f32>(lane, "speed")?,
+                        parse_shape(required(lane, "shape")?)?,
+                    ))
+                })
+                .collect::<Result<Vec<_>, SumoError>>()?;
+            lanes.sort_by_key(|(index, ..)| *index);
+
+            for (_, lane_id, speed, shape) in lanes.iter() {
+                let ids = (next_id..next_id + shape.len() as u32).collect::<Vec<_>>();
+                next_id += shape.len() as u32;
+
+                for (index, (id, location)) in ids.iter().zip(shape).enumerate() {
+                    nodes.insert(
+                        *id,
+                        Node::new(
+                            *id,
+                            *location,
+                            *speed,
+                            ids.get(index + 1).into_iter().copied().collect(),
+                            None,
+                            None,
+                        ),
+                    );
+                }
+
+                lane_nodes.insert(lane_id.clone(), ids);
+            }
+
+            edge_lanes.insert(
+                edge_id.to_string(),
+                lanes
+                    .into_iter()
+                    .map(|(index, id, ..)| (index, id))
+                    .collect::<Vec<_>>(),
+            );
+        }
+
+        for lanes in edge_lanes.values() {
+            for pair in lanes.windows(2) {
+                let (right, left) = (&lane_nodes[&pair[0].1], &lane_nodes[&pair[1].1]);
+                for node in right {
+                    let adjacent_node = closest_node(&nodes, *node, left);
+                    nodes
+                        .get_mut(node)
+                        .unwrap()
+                        .set_adjacent_node_left(Some(adjacent_node));
+                }
+                for node in left {
+                    let adjacent_node = closest_node(&nodes, *node, right);
+                    nodes
+                        .get_mut(node)
+                        .unwrap()
+                        .set_adjacent_node_right(Some(adjacent_node));
+                }
+            }
+        }
+
+        // The link nodes with the light they belong to and their index in its phases
+        let mut signalised_links = Vec::new();
+        for connection in root
+            .children()
+            .filter(|node| node.has_tag_name("connection"))
+        {
+            let from_lane = format!(
+                "{}_{}",
+                required(connection, "from")?,
+                required(connection, "fromLane")?
+            );
+            let to_lane = format!(
+                "{}_{}",
+                required(connection, "to")?,
+                required(connection, "toLane")?
+            );
+            let (Some(from_nodes), Some(to_nodes)) =
+                (lane_nodes.get(&from_lane), lane_nodes.get(&to_lane))
+            else {
+                // Internal or non-road lanes
+                continue;
+            };
+            let (from, to) = (*from_nodes.last().unwrap(), to_nodes[0]);
+
+            let target = match connection.attribute("tl") {
+                Some(light) => {
+                    let link_index = parse::<usize>(connection, "linkIndex")?;
+
+                    let (from_location, to_location) =
+                        (nodes[&from].location(), nodes[&to].location());
+                    let direction = (to_location - from_location)
+                        .try_normalize(f32::EPSILON)
+                        .unwrap_or_default();
+                    let offset =
+                        LINK_NODE_OFFSET.min((to_location - from_location).magnitude() / 2.0);
+
+                    let link_node = next_id;
+                    next_id += 1;
+                    nodes.insert(
+                        link_node,
+                        Node::new(
+                            link_node,
+                            from_location + direction * offset,
+                            nodes[&from].max_speed(),
+                            vec![to],
+                            None,
+                            None,
+                        ),
+                    );
+                    signalised_links.push((link_node, light.to_string(), link_index));
+
+                    link_node
+                }
+                None => to,
+            };
+
+            nodes.get_mut(&from).unwrap().add_next_node(target);
+        }
+
+        let mut programs = HashMap::new();
+        for program in root.children().filter(|node| node.has_tag_name("tlLogic")) {
+            let id = required(program, "id")?;
+            if programs.contains_key(id) {
+                continue;
+            }
+
+            let phases = program
+                .children()
+                .filter(|node| node.has_tag_name("phase"))
+                .map(|phase| {
+                    Ok((
+                        parse::<f32>(phase, "duration")?,
+                        required(phase, "state")?.chars().collect::<Vec<_>>(),
+                    ))
+                })
+                .collect::<Result<Vec<_>, SumoError>>()?;
+            let offset = match program.attribute("offset") {
+                Some(_) => parse::<f32>(program, "offset")?,
+                None => 0.0,
+            };
+
+            programs.insert(id.to_string(), (phases, offset));
+        }
+
+        let mut traffic_lights = Vec::new();
+        for (link_node, light, link_index) in signalised_links {
+            let Some((phases, offset)) = programs.get(&light) else {
+                continue;
+            };
+
+            let schema = phases
+                .iter()
+                .map(|(duration, states)| {
+                    let state = states.get(link_index).ok_or(SumoError::InvalidValue {
+                        attribute: "linkIndex",
+                        value: link_index.to_string(),
+                    })?;
+                    Ok((*duration, light_state(*state)?))
+                })
+                .collect::<Result<Vec<_>, SumoError>>()?;
+            if schema.is_empty() {
+                continue;
+            }
+
+            traffic_lights.push(TimedTrafficLight::new(
+                link_node,
+                shift_schema(schema, *offset),
+            ));
+        }
+
+        let mut network = RoadNetwork::new(nodes);
+        let mut unsupported_projection = None;
+        if let Some(location) = root.children().find(|node| node.has_tag_name("location")) {
+            let reference = parse_location(location)?;
+            let parameters = required(location, "projParameter")?.trim();
+            if reference.is_none() && parameters != "!" {
+                unsupported_projection = Some(parameters.to_string());
+            }
+            network.set_coordinate_reference(reference);
+        }
+
+        Ok(Self {
+            network,
+            traffic_lights,
+            lane_nodes,
+            edge_lanes,
+            unsupported_projection,
+        })
+    }
+
+    /// The `projParameter` of the `<location>` if the network was imported without a
+    /// coordinate reference because its projection isn't supported
+    pub fn unsupported_projection(&self) -> Option<&str> {
+        self.unsupported_projection.as_deref()
+    }
+
+    pub fn road_network(&self) -> &RoadNetwork {
+        &self.network
+    }
+
+    pub fn traffic_lights(&self) -> &[TimedTrafficLight] {
+        self.traffic_lights.as_ref()
+    }
+
+    /// The nodes along the lane with the given SUMO id, in driving direction
+    pub fn lane_nodes(&self, lane: &str) -> Option<&[u32]> {
+        self.lane_nodes.get(lane).map(Vec::as_slice)
+    }
+
+    /// The SUMO indices and ids of the road lanes of the edge, from the rightmost to the
+    /// leftmost lane. The indices count the lanes that aren't imported too, like sidewalks.
+    pub fn edge_lanes(&self, edge: &str) -> Option<&[(u32, String)]> {
+        self.edge_lanes.get(edge).map(Vec::as_slice)
+    }
+
+    /// The SUMO id of the lane of the edge with the given index
+    fn lane(&self, edge: &str, index: u32) -> Result<&str, SumoError> {
+        self.edge_lanes(edge)
+            .ok_or_else(|| SumoError::UnknownEdge(edge.to_string()))?
+            .iter()
+            .find(|(lane_index, _)| *lane_index == index)
+            .map(|(_, lane)| lane.as_str())
+            .ok_or_else(|| SumoError::InvalidValue {
+                attribute: "departLane",
+                value: index.to_string(),
+            })
+    }
+
+    /// The nodes to drive along the edges, following the lanes that are connected to each
+    /// other. The first lane can be chosen by its SUMO index. None if the edges aren't connected.
+    pub fn route_nodes(
+        &self,
+        edges: &[&str],
+        first_lane: Option<u32>,
+    ) -> Result<Option<Vec<u32>>, SumoError> {
+        let lanes = |edge: &str| {
+            self.edge_lanes
+                .get(edge)
+                .ok_or_else(|| SumoError::UnknownEdge(edge.to_string()))
+        };
+
+        let Some((first_edge, other_edges)) = edges.split_first() else {
+            return Ok(None);
+        };
+
+        // The paths that end at the lanes of the edge we're at
+        let mut paths = match first_lane {
+            Some(index) => vec![self.lane_nodes[self.lane(first_edge, index)?].clone()],
+            None => lanes(first_edge)?
+                .iter()
+                .map(|(_, lane)| self.lane_nodes[lane].clone())
+                .collect(),
+        };
+
+        for edge in other_edges {
+            paths = lanes(edge)?
+                .iter()
+                .filter_map(|(_, lane)| {
+                    let lane_nodes = &self.lane_nodes[lane];
+                    paths.iter().find_map(|path| {
+                        let mut path = path.clone();
+                        path.extend(self.connection(*path.last().unwrap(), lane_nodes[0])?);
+                        path.extend(lane_nodes);
+                        Some(path)
+                    })
+                })
+                .collect();
+        }
+
+        Ok(paths.into_iter().next())
+    }
+
+    /// The nodes between the end of a lane and the start of the next one, if they're connected
+    fn connection(&self, from: u32, to: u32) -> Option<Vec<u32>> {
+        let next_nodes = self.network.find_node(from).next_node_ids();
+        if next_nodes.contains(&to) {
+            return Some(Vec::new());
+        }
+
+        next_nodes
+            .iter()
+            .find(|link_node| {
+                self.network
+                    .find_node(**link_node)
+                    .next_node_ids()
+                    .contains(&to)
+            })
+            .map(|link_node| vec![*link_node])
+    }
+
+    pub fn into_simulator(self) -> Simulator {
+        Simulator::new(
+            self.network,
+            self.traffic_lights
+                .into_iter()
+                .map(|light| Box::new(light) as Box<dyn TrafficLight>)
+                .collect(),
+        )
+    }
+}
+
+/// The vehicles of a SUMO `.rou.xml` file, ready to depart in a network converted from SUMO.
+///
+/// Vehicles, flows and trips are supported. Vehicles and flows with a route follow it as a
+/// fixed route, trips are routed by the simulator. From the vehicle types the class, length,
+/// width, accel, decel, speedFactor and tau are used. Flows with a probability are expanded
+/// with a fixed seed, so importing the same file twice gives the same departures.
+pub struct SumoRoutes {
+    departures: Vec<(f32, RoadUser)>,
+}
+
+impl SumoRoutes {
+    pub fn load(path: impl AsRef<Path>, network: &SumoNetwork) -> Result<Self, SumoError> {
+        Self::from_xml(&std::fs::read_to_string(path)?, network)
+    }
+
+    pub fn from_xml(xml: &str, network: &SumoNetwork) -> Result<Self, SumoError> {
+        let document = roxmltree::Document::parse(xml)?;
+        let root = document.root_element();
+
+        let mut vehicle_types = HashMap::new();
+        let mut routes = HashMap::new();
+        for element in root.children().filter(|node| node.is_element()) {
+            match element.tag_name().name() {
+                "vType" => {
+                    vehicle_types.insert(
+                        required(element, "id")?.to_string(),
+                        VehicleType::parse(element)?,
+                    );
+                }
+                "route" => {
+                    routes.insert(required(element, "id")?, required(element, "edges")?);
+                }
+                _ => {}
+            }
+        }
+
+        let mut rng = ChaCha8Rng::seed_from_u64(0);
+        let mut departures = Vec::new();
+        for element in root.children().filter(|node| node.is_element()) {
+            let departure_times = match element.tag_name().name() {
+                "vehicle" | "trip" => vec![parse::<f32>(element, "depart")?],
+                "flow" => flow_departures(element, &mut rng)?,
+                _ => continue,
+            };
+
+            let vehicle_type = match element.attribute("type") {
+                Some(id) => *vehicle_types
+                    .get(id)
+                    .ok_or_else(|| SumoError::UnknownVehicleType(id.to_string()))?,
+                None => VehicleType::default(),
+            };
+            let itinerary = Itinerary::parse(element, &routes, network)?;
+
+            for time in departure_times {
+                departures.push((time, itinerary.road_user(element, &vehicle_type, network)?));
+            }
+        }
+        departures.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+        Ok(Self { departures })
+    }
+
+    pub fn departures(&self) -> impl Iterator<Item = (f32, &RoadUser)> + '_ {
+        self.departures.iter().map(|(time, user)| (*time, user))
+    }
+
+    /// Lets the vehicles depart at their times
+    pub fn schedule(self, simulator: &mut Simulator) {
+        for (time, user) in self.departures {
+            simulator.schedule_departure(time, user);
+        }
+    }
+}
+
+/// A `vType`. Values that aren't set fall back to the defaults of the class.
+#[derive(Debug, Clone, Copy, Default)]
+struct VehicleType {
+    class: VehicleClass,
+    length: Option<f32>,       // m
+    width: Option<f32>,        // m
+    acceleration: Option<f32>, // m/s/s
+    deceleration: Option<f32>, // m/s/s
+    speed_factor: Option<f32>,
+    time_headway: Option<f32>, // s
+}
+
+impl VehicleType {
+    fn parse(element: roxmltree::Node) -> Result<Self, SumoError> {
+        let class = match element.attribute("vClass") {
+            Some("bus" | "coach") => VehicleClass::Bus,
+            Some("truck" | "trailer" | "delivery") => VehicleClass::Truck,
+            Some("evehicle") => VehicleClass::ElectricCar,
+            _ => VehicleClass::PassengerCar,
+        };
+
+        Ok(Self {
+            class,
+            length: optional(element, "length")?,
+            width: optional(element, "width")?,
+            acceleration: optional(element, "accel")?,
+            deceleration: optional(element, "decel")?,
+            speed_factor: optional(element, "speedFactor")?,
+            time_headway: optional(element, "tau")?,
+        })
+    }
+
+    /// None if the type doesn't say anything about the driver, so it's drawn from the
+    /// driver distribution of the simulator
+    fn driver_behaviour(&self) -> Option<DriverBehaviour> {
+        if self.speed_factor.is_none() && self.time_headway.is_none() {
+            return None;
+        }
+
+        let default = DriverBehaviour::default();
+        Some(DriverBehaviour {
+            desired_speed_factor: self.speed_factor.unwrap_or(default.desired_speed_factor),
+            time_headway: self.time_headway.unwrap_or(default.time_headway),
+            ..default
+        })
+    }
+}
+
+/// Where a vehicle or flow drives
+enum Itinerary {
+    Route(Vec<u32>),
+    Trip { origin: u32, destination: u32 },
+}
+
+impl Itinerary {
+    fn parse(
+        element: roxmltree::Node,
+        routes: &HashMap<&str, &str>,
+        network: &SumoNetwork,
+    ) -> Result<Self, SumoError> {
+        let id = required(element, "id")?;
+        let first_lane = match element.attribute("departLane") {
+            Some(lane) => lane.parse::<u32>().ok(),
+            None => None,
+        };
+
+        let edges = match element.attribute("route") {
+            Some(route) => Some(
+                *routes
+                    .get(route)
+                    .ok_or_else(|| SumoError::UnknownRoute(route.to_string()))?,
+            ),
+            None => element
+                .children()
+                .find(|node| node.has_tag_name("route"))
+                .map(|route| required(route, "edges"))
+                .transpose()?,
+        };
+
+        if let Some(edges) = edges {
+            let edges = edges.split_whitespace().collect::<Vec<_>>();
+            return match network.route_nodes(&edges, first_lane)? {
+                Some(route) if route.len() >= 2 => Ok(Itinerary::Route(route)),
+                _ => Err(SumoError::DisconnectedRoute(id.to_string())),
+            };
+        }
+
+        // Without a lane index the trip starts and ends on the rightmost lane
+        let lane = |edge: &str, index: Option<u32>| {
+            let lane = match index {
+                Some(index) => network.lane(edge, index)?,
+                None => network
+                    .edge_lanes(edge)
+                    .and_then(|lanes| lanes.first())
+                    .map(|(_, lane)| lane.as_str())
+                    .ok_or_else(|| SumoError::UnknownEdge(edge.to_string()))?,
+            };
+            Ok::<_, SumoError>(&network.lane_nodes[lane])
+        };
+        Ok(Itinerary::Trip {
+            origin: lane(required(element, "from")?, first_lane)?[0],
+            destination: *lane(required(element, "to")?, None)?.last().unwrap(),
+        })
+    }
+
+    /// The id is replaced when the road user departs
+    fn road_user(
+        &self,
+        element: roxmltree::Node,
+        vehicle_type: &VehicleType,
+        network: &SumoNetwork,
+    ) -> Result<RoadUser, SumoError> {
+        let id = required(element, "id")?;
+        let network = network.road_network();
+        let class = vehicle_type.class;
+
+        let user = match self {
+            Itinerary::Route(route) => {
+                let depart_speed = match element.attribute("departSpeed") {
+                    Some("max" | "desired" | "speedLimit") => {
+                        network.find_node(route[0]).max_speed()
+                    }
+                    Some(speed) => speed.parse::<f32>().unwrap_or_default(),
+                    None => 0.0,
+                };
+
+                RoadUser::new(
+                    0,
+                    network.find_node(route[0]).location(),
+                    depart_speed,
+                    class.default_acceleration(),
+                    class.default_deceleration(),
+                    class.default_max_steering_angle(),
+                    route[1],
+                    *route.last().unwrap(),
+                    network,
+                )
+                .with_class(class)
+                .with_fixed_route(route[1..].to_vec())
+            }
+            Itinerary::Trip {
+                origin,
+                destination,
+            } => RoadUser::departing_from(
+                0,
+                class,
+                *origin,
+                *destination,
+                network,
+                &RoadClosures::default(),
+            )
+            .ok_or_else(|| SumoError::DisconnectedRoute(id.to_string()))?,
+        };
+
+        let mut user = user
+            .with_dimensions(
+                vehicle_type.length.unwrap_or(class.default_length()),
+                vehicle_type.width.unwrap_or(class.default_width()),
+            )
+            .with_acceleration(
+                vehicle_type
+                    .acceleration
+                    .unwrap_or(class.default_acceleration()),
+                vehicle_type
+                    .deceleration
+                    .unwrap_or(class.default_deceleration()),
+            );
+        if let Some(driver_behaviour) = vehicle_type.driver_behaviour() {
+            user = user.with_driver_behaviour(driver_behaviour);
+        }
+
+        Ok(user)
+    }
+}
+
+/// The departure times of the vehicles of a flow
+fn flow_departures(flow: roxmltree::Node, rng: &mut impl Rng) -> Result<Vec<f32>, SumoError> {
+    let begin = optional::<f32>(flow, "begin")?.unwrap_or_default();
+    let end = optional::<f32>(flow, "end")?.unwrap_or(DEFAULT_FLOW_END);
+    let number = optional::<usize>(flow, "number")?;
+
+    let period = if let Some(period) = optional::<f32>(flow, "period")? {
+        Some(period)
+    } else if let Some(vehicles_per_hour) = optional::<f32>(flow, "vehsPerHour")? {
+        Some(3600.0 / vehicles_per_hour)
+    } else if optional::<f32>(flow, "probability")?.is_some() {
+        None
+    } else {
+        number.map(|number| (end - begin) / number as f32)
+    };
+
+    let departures = match period {
+        Some(period) if period > 0.0 => (0..)
+            .map(|index| begin + index as f32 * period)
+            .take_while(|time| *time < end)
+            .take(number.unwrap_or(usize::MAX))
+            .collect(),
+        Some(_) => Vec::new(),
+        None => {
+            // A chance of a departure every second
+            let probability = parse::<f32>(flow, "probability")?;
+            (0..)
+                .map(|second| begin + second as f32)
+                .take_while(|time| *time < end)
+                .filter(|_| rng.gen::<f32>() < probability)
+                .take(number.unwrap_or(usize::MAX))
+                .collect()
+        }
+    };
+
+    Ok(departures)
+}
+
+fn optional<T: std::str::FromStr>(
+    node: roxmltree::Node,
+    attribute: &'static str,
+) -> Result<Option<T>, SumoError> {
+    match node.attribute(attribute) {
+        Some(_) => parse(node, attribute).map(Some),
+        None => Ok(None),
+    }
+}
+
+fn required<'a>(
+    node: roxmltree::Node<'a, '_>,
+    attribute: &'static str,
+) -> Result<&'a str, SumoError> {
+    node.attribute(attribute)
+        .ok_or_else(|| SumoError::MissingAttribute {
+            element: node.tag_name().name().to_string(),
+            attribute,
+        })
+}
+
+fn parse<T: std::str::FromStr>(
+    node: roxmltree::Node,
+    attribute: &'static str,
+) -> Result<T, SumoError> {
+    let value = required(node, attribute)?;
+    value.parse().map_err(|_| SumoError::InvalidValue {
+        attribute,
+        value: value.to_string(),
+    })
+}
+
+/// The projection of a `<location>`, with the `netOffset` that moved the projected
+/// coordinates to the network coordinates. Only UTM and transverse Mercator projections
+/// are supported, for others and for `!`, which means the network isn't georeferenced,
+/// there's none.
+fn parse_location(location: roxmltree::Node) -> Result<Option<TransverseMercator>, SumoError> {
+    let parameters = required(location, "projParameter")?;
+    if parameters.trim() == "!" {
+        return Ok(None);
+    }
+
+    let invalid = || SumoError::InvalidValue {
+        attribute: "projParameter",
+        value: parameters.to_string(),
+    };
+    let parameter = |name: &str| {
+        parameters
+            .split_whitespace()
+            .filter_map(|parameter| parameter.strip_prefix('+'))
+            .find_map(|parameter| match parameter.split_once('=') {
+                Some((key, value)) if key == name => Some(value),
+                None if parameter == name => Some(""),
+                _ => None,
+            })
+    };
+    let number = |name: &str, default: f64| match parameter(name) {
+        Some(value) => value.parse::<f64>().map_err(|_| invalid()),
+        None => Ok(default),
+    };
+
+    let projection = match parameter("proj") {
+        Some("utm") => TransverseMercator::utm(
+            parameter("zone")
+                .and_then(|zone| zone.parse().ok())
+                .filter(|zone| (1..=60).contains(zone))
+                .ok_or_else(invalid)?,
+            parameter("south").is_none(),
+        ),
+        Some("tmerc") => TransverseMercator::new(
+            number("lon_0", 0.0)?,
+            number("lat_0", 0.0)?,
+            number("k", number("k_0", 1.0)?)?,
+            number("x_0", 0.0)?,
+            number("y_0", 0.0)?,
+        ),
+        _ => return Ok(None),
+    };
+
+    let offset = match location.attribute("netOffset") {
+        Some(offset) => offset
+            .split_once(',')
+            .and_then(|(x, y)| Some((x.trim().parse().ok()?, y.trim().parse().ok()?)))
+            .ok_or_else(|| SumoError::InvalidValue {
+                attribute: "netOffset",
+                value: offset.to_string(),
+            })?,
+        None => (0.0, 0.0),
+    };
+
+    Ok(Some(projection.with_offset(offset.0, offset.1)))
+}
+
+/// Parses `x,y[,z] x,y[,z] ...`, leaving out points that are at the same place as the one
+/// before them
+fn parse_shape(shape: &str) -> Result<Vec<Point3<f32>>, SumoError> {
+    let invalid = || SumoError::InvalidValue {
+        attribute: "shape",
+        value: shape.to_string(),
+    };
+
+    let mut points: Vec<Point3<f32>> = Vec::new();
+    for point in shape.split_whitespace() {
+        let coordinates = point
+            .split(',')
+            .map(|coordinate| coordinate.parse::<f32>().map_err(|_| invalid()))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let point = match coordinates[..] {
+            [x, y] => Point3::new(x, y, 0.0),
+            [x, y, z] => Point3::new(x, y, z),
+            _ => return Err(invalid()),
+        };
+
+        if points.last() != Some(&point) {
+            points.push(point);
+        }
+    }
+
+    if points.is_empty() {
+        return Err(invalid());
+    }
+
+    Ok(points)
+}
+
+fn is_road_lane(allow: Option<&str>) -> bool {
+    allow.is_none_or(|allow| {
+        allow
+            .split_whitespace()
+            .any(|class| !NON_ROAD_CLASSES.contains(&class))
+    })
+}
+
+fn closest_node(nodes: &HashMap<u32, Node>, node: u32, candidates: &[u32]) -> u32 {
+    let location = nodes[&node].location();
+    *candidates
+        .iter()
+        .min_by(|a, b| {
+            let distance_a = (nodes[a].location() - location).magnitude();
+            let distance_b = (nodes[b].location() - location).magnitude();
+            distance_a.total_cmp(&distance_b)
+        })
+        .unwrap()
+}
+
+fn light_state(state: char) -> Result<TrafficLightState, SumoError> {
+    match state {
+        // A light that's off lets everyone through
+        'G' | 'g' | 'O' | 'o' => Ok(TrafficLightState::Green),
+        'y' | 'Y' => Ok(TrafficLightState::Orange),
+        'r' | 'R' | 's' | 'u' => Ok(TrafficLightState::Red),
+        _ => Err(SumoError::InvalidValue {
+            attribute: "state",
+            value: state.to_string(),
+        }),
+    }
+}
+
+/// SUMO starts a program at its offset. The timed lights start at the beginning of their
+/// schema, so the schema is rotated to where the program is at time zero.
+fn shift_schema(
+    schema: Vec<(f32, TrafficLightState)>,
+    offset: f32,
+) -> Vec<(f32, TrafficLightState)> {
+    let cycle: f32 = schema.iter().map(|(duration, _)| *duration).sum();
+    if cycle <= 0.0 {
+        return schema;
+    }
+
+    let mut start = (-offset).rem_euclid(cycle);
+    if start == 0.0 {
+        return schema;
+    }
+
+    let mut shifted = Vec::new();
+    let mut skipped = Vec::new();
+    for (duration, state) in schema {
+        if start >= duration {
+            start -= duration;
+            skipped.push((duration, state));
+        } else if start > 0.0 {
+            shifted.push((duration - start, state));
+            skipped.push((start, state));
+            start = 0.0;
+        } else {
+            shifted.push((duration, state));
+        }
+    }
+
+    shifted.extend(skipped);
+    shifted
+}
@@ -0,0 +1,141 @@
+use std::io::{self, Write};
+
+use crate::{
+    road::RoadNetwork,
+    traffic_light::{TrafficLight, TrafficLightState},
+};
+
+const MARGIN: f32 = 10.0; // m
+const NODE_RADIUS: f32 = 0.5; // m
+const LIGHT_RADIUS: f32 = 1.2; // m
+const FONT_SIZE: f32 = 2.0; // m
+
+/// Draws the network to scale, 1 unit is 1 meter, seen from above with north up.
+/// Next nodes are connected with arrows, adjacent lanes with dashed lines, and traffic
+/// lights are circles in the colour of their current state around their node.
+pub fn write_network(
+    network: &RoadNetwork,
+    traffic_lights: &[Box<dyn TrafficLight>],
+    mut writer: impl Write,
+) -> io::Result<()> {
+    let mut node_ids = network.all_node_ids().collect::<Vec<_>>();
+    node_ids.sort();
+
+    let (min, max) = network.bounds();
+
+    // SVG has the y axis pointing down
+    writeln!(
+        writer,
+        r#"<svg xmlns="http://www.w3.org/2000/svg" viewBox="{} {} {} {}" width="{}" height="{}">"#,
+        min.x - MARGIN,
+        -max.y - MARGIN,
+        max.x - min.x + 2.0 * MARGIN,
+        max.y - min.y + 2.0 * MARGIN,
+        max.x - min.x + 2.0 * MARGIN,
+        max.y - min.y + 2.0 * MARGIN,
+    )?;
+    writeln!(
+        writer,
+        r#"<defs><marker id="arrow" viewBox="0 0 10 10" refX="10" refY="5" markerWidth="6" markerHeight="6" orient="auto-start-reverse"><path d="M 0 0 L 10 5 L 0 10 z" fill="black"/></marker></defs>"#
+    )?;
+
+    writeln!(
+        writer,
+        r#"<g id="adjacent-lanes" stroke="grey" stroke-width="0.2" stroke-dasharray="1 1">"#
+    )?;
+    for id in node_ids.iter() {
+        let node = network.find_node(*id);
+        for adjacent in [node.adjacent_node_right_id(), node.adjacent_node_left_id()]
+            .into_iter()
+            .flatten()
+        {
+            // Lanes next to each other usually point at each other, so draw them once
+            let mutual = [
+                network.find_node(adjacent).adjacent_node_right_id(),
+                network.find_node(adjacent).adjacent_node_left_id(),
+            ]
+            .contains(&Some(*id));
+            if mutual && adjacent < *id {
+                continue;
+            }
+
+            let (from, to) = (node.location(), network.find_node(adjacent).location());
+            writeln!(
+                writer,
+                r#"<line x1="{}" y1="{}" x2="{}" y2="{}"/>"#,
+                from.x, -from.y, to.x, -to.y
+            )?;
+        }
+    }
+    writeln!(writer, "</g>")?;
+
+    writeln!(
+        writer,
+        r#"<g id="edges" stroke="black" stroke-width="0.3" marker-end="url(#arrow)">"#
+    )?;
+    for id in node_ids.iter() {
+        let node = network.find_node(*id);
+        for next in node.next_node_ids() {
+            let (from, to) = (node.location(), network.find_node(*next).location());
+            // End the arrow at the edge of the node instead of its centre
+            let length = (to - from).magnitude();
+            let to = if length > NODE_RADIUS {
+                to - (to - from) * (NODE_RADIUS / length)
+            } else {
+                to
+            };
+            writeln!(
+                writer,
+                r#"<line x1="{}" y1="{}" x2="{}" y2="{}"/>"#,
+                from.x, -from.y, to.x, -to.y
+            )?;
+        }
+    }
+    writeln!(writer, "</g>")?;
+
+    writeln!(
+        writer,
+        r#"<g id="traffic-lights" fill="none" stroke-width="0.4">"#
+    )?;
+    for light in traffic_lights
+        .iter()
+        .filter(|light| network.contains_node(light.node()))
+    {
+        let location = network.find_node(light.node()).location();
+        writeln!(
+            writer,
+            r#"<circle cx="{}" cy="{}" r="{LIGHT_RADIUS}" stroke="{}"/>"#,
+            location.x,
+            -location.y,
+            colour(light.get_state())
+        )?;
+    }
+    writeln!(writer, "</g>")?;
+
+    writeln!(
+        writer,
+        r#"<g id="nodes" font-size="{FONT_SIZE}" font-family="sans-serif">"#
+    )?;
+    for id in node_ids.iter() {
+        let location = network.find_node(*id).location();
+        writeln!(
+            writer,
+            r#"<circle cx="{}" cy="{}" r="{NODE_RADIUS}" fill="steelblue"/><text x="{}" y="{}">{id}</text>"#,
+            location.x,
+            -location.y,
+            location.x + NODE_RADIUS,
+            -location.y - NODE_RADIUS,
+        )?;
+    }
+    writeln!(writer, "</g>")?;
+
+    writeln!(writer, "</svg>")
+}
+
+fn colour(state: TrafficLightState) -> &'static str {
+    match state {
+        TrafficLightState::Red => "red",
+        TrafficLightState::Orange => "orange",
+        TrafficLightState::Green => "green",
+    }
+}
@@ -1,12 +1,37 @@
 use std::fmt::Debug;
 
-pub trait TrafficLight: Debug {
+use serde::{Deserialize, Serialize};
+
+use crate::detector::Detector;
+
+/// Lights are `Send + Sync`, like the rest of the simulator, so it can be used across threads
+pub trait TrafficLight: CloneTrafficLight + Debug + Send + Sync {
     fn node(&self) -> u32;
+    /// Called before every tick for lights that react to traffic
+    fn observe_detectors(&mut self, _detectors: &[Detector]) {}
     fn tick(&mut self, current_time: f32);
     fn get_state(&self) -> TrafficLightState;
 }
 
-#[derive(Debug)]
+/// Implemented for every light that is `Clone`, so boxed lights can be cloned with the simulator
+pub trait CloneTrafficLight {
+    /// A copy of the light in its current state
+    fn clone_box(&self) -> Box<dyn TrafficLight>;
+}
+
+impl<T: TrafficLight + Clone + 'static> CloneTrafficLight for T {
+    fn clone_box(&self) -> Box<dyn TrafficLight> {
+        Box::new(self.clone())
+    }
+}
+
+impl Clone for Box<dyn TrafficLight> {
+    fn clone(&self) -> Self {
+        self.clone_box()
+    }
+}
+
+#[derive(Debug, Clone)]
 pub struct TimedTrafficLight {
     node: u32,
     current_state: TrafficLightState,
@@ -49,7 +74,7 @@ impl TrafficLight for TimedTrafficLight {
     }
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum TrafficLightState {
     Red,
     Orange,
@@ -0,0 +1,160 @@
+use std::io::{self, Write};
+
+use nalgebra::Point3;
+
+use crate::{user::RoadUser, vehicle::VehicleClass, TIME_EPSILON};
+
+/// Floating car data of a single road user at one moment
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TrajectoryPoint {
+    pub time: f32, // s
+    pub road_user: u32,
+    pub class: VehicleClass,
+    pub position: Point3<f32>,
+    /// Degrees clockwise from north, like SUMO
+    pub heading: f32,
+    pub speed: f32,        // m/s
+    pub acceleration: f32, // m/s/s
+    /// The node the road user is driving to
+    pub node: u32,
+}
+
+impl TrajectoryPoint {
+    pub fn of(time: f32, user: &RoadUser) -> Self {
+        Self {
+            time,
+            road_user: user.id,
+            class: user.class(),
+            position: user.location(),
+            heading: user.heading(),
+            speed: user.current_speed(),
+            acceleration: user.current_acceleration(),
+            node: user.next_node(),
+        }
+    }
+}
+
+/// The trajectories of all road users, sampled at a fixed interval
+#[derive(Debug, Clone, PartialEq)]
+pub struct Trajectories {
+    interval: f32, // s
+    next_sample: f32,
+    points: Vec<TrajectoryPoint>, // In order of time
+}
+
+impl Trajectories {
+    pub fn new(interval: f32) -> Self {
+        Self {
+            interval,
+            next_sample: 0.0,
+            points: Vec::new(),
+        }
+    }
+
+    pub fn interval(&self) -> f32 {
+        self.interval
+    }
+
+    pub fn points(&self) -> &[TrajectoryPoint] {
+        self.points.as_ref()
+    }
+
+    /// The points of one road user
+    pub fn road_user(&self, id: u32) -> impl Iterator<Item = &TrajectoryPoint> + '_ {
+        self.points
+            .iter()
+            .filter(move |point| point.road_user == id)
+    }
+
+    /// Keeps sampling at the same times, without the points taken so far
+    pub(crate) fn without_points(&self) -> Self {
+        Self {
+            interval: self.interval,
+            next_sample: self.next_sample,
+            points: Vec::new(),
+        }
+    }
+
+    pub(crate) fn into_points_before(mut self, time: f32) -> Vec<TrajectoryPoint> {
+        self.points.retain(|point| point.time < time);
+        self.points
+    }
+
+    pub(crate) fn set_points(&mut self, points: Vec<TrajectoryPoint>) {
+        self.points = points;
+    }
+
+    /// Takes a sample if the interval has passed since the last one
+    pub(crate) fn record(&mut self, time: f32, road_users: &[RoadUser]) {
+        if time + TIME_EPSILON < self.next_sample {
+            return;
+        }
+
+        self.points.extend(
+            road_users
+                .iter()
+                .map(|user| TrajectoryPoint::of(time, user)),
+        );
+        self.next_sample =
+            ((time + TIME_EPSILON) / self.interval).floor() * self.interval + self.interval;
+    }
+
+    /// One row per point, with a header
+    pub fn write_csv(&self, mut writer: impl Write) -> io::Result<()> {
+        writeln!(
+            writer,
+            "time,id,class,x,y,z,heading,speed,acceleration,node"
+        )?;
+
+        for point in self.points.iter() {
+            writeln!(
+                writer,
+                "{:.2},{},{:?},{:.2},{:.2},{:.2},{:.2},{:.2},{:.2},{}",
+                point.time,
+                point.road_user,
+                point.class,
+                point.position.x,
+                point.position.y,
+                point.position.z,
+                point.heading,
+                point.speed,
+                point.acceleration,
+                point.node
+            )?;
+        }
+
+        Ok(())
+    }
+
+    /// In the format of SUMO's `--fcd-output`. The node is written as an extra attribute.
+    pub fn write_fcd_xml(&self, mut writer: impl Write) -> io::Result<()> {
+        writeln!(writer, r#"<?xml version="1.0" encoding="UTF-8"?>"#)?;
+        writeln!(writer, "<fcd-export>")?;
+
+        let mut points = self.points.iter().peekable();
+        while let Some(first) = points.peek() {
+            let time = first.time;
+            writeln!(writer, r#"    <timestep time="{time:.2}">"#)?;
+
+            while let Some(point) = points.next_if(|point| point.time == time) {
+                writeln!(
+                    writer,
+                    r#"        <vehicle id="{}" x="{:.2}" y="{:.2}" z="{:.2}" angle="{:.2}" type="{:?}" speed="{:.2}" acceleration="{:.2}" node="{}"/>"#,
+                    point.road_user,
+                    point.position.x,
+                    point.position.y,
+                    point.position.z,
+                    point.heading,
+                    point.class,
+                    point.speed,
+                    point.acceleration,
+                    point.node
+                )?;
+            }
+
+            writeln!(writer, "    </timestep>")?;
+        }
+
+        writeln!(writer, "</fcd-export>")
+    }
+}
@@ -0,0 +1,394 @@
+use crate::{road::RoadNetwork, user::RoadUser, vehicle::VehicleClass};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Departures {
+    /// Explicit departure times (s) from the first node of the route
+    Timetable(Vec<f32>),
+    /// A departure every `headway` seconds, starting at `first` up to and including `last`.
+    /// The headway must be positive.
+    Headway { first: f32, headway: f32, last: f32 },
+}
+
+impl Departures {
+    /// All departures that fall in the window `[from, to)`
+    pub fn between(&self, from: f32, to: f32) -> Vec<f32> {
+        self.assert_valid();
+
+        match self {
+            Departures::Timetable(times) => times
+                .iter()
+                .copied()
+                .filter(|time| (from..to).contains(time))
+                .collect(),
+            Departures::Headway {
+                first,
+                headway,
+                last,
+            } => {
+                let first_index = ((from - first) / headway).ceil().max(0.0) as u32;
+
+                (first_index..)
+                    .map(|index| first + index as f32 * headway)
+                    .take_while(|time| *time < to && *time <= *last)
+                    .collect()
+            }
+        }
+    }
+
+    fn assert_valid(&self) {
+        if let Departures::Headway { headway, .. } = self {
+            assert!(*headway > 0.0, "A transit headway must be positive");
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TransitStop {
+    node: u32,
+    scheduled_offset: f32, // s after the departure of the trip
+    dwell_time: f32,       // s
+    has_bay: bool,
+}
+
+impl TransitStop {
+    pub fn new(node: u32, scheduled_offset: f32, dwell_time: f32, has_bay: bool) -> Self {
+        Self {
+            node,
+            scheduled_offset,
+            dwell_time,
+            has_bay,
+        }
+    }
+
+    pub fn node(&self) -> u32 {
+        self.node
+    }
+
+    pub fn scheduled_offset(&self) -> f32 {
+        self.scheduled_offset
+    }
+
+    pub fn dwell_time(&self) -> f32 {
+        self.dwell_time
+    }
+
+    /// When true, a dwelling vehicle pulls out of the lane and doesn't block the traffic behind it
+    pub fn has_bay(&self) -> bool {
+        self.has_bay
+    }
+}
+
+/// A public transport line that follows a fixed route through the network
+#[derive(Debug, Clone)]
+pub struct TransitLine {
+    id: u32,
+    route: Vec<u32>,
+    stops: Vec<TransitStop>,
+    departures: Departures,
+    vehicle_class: VehicleClass,
+}
+
+impl TransitLine {
+    /// The stops must be given in the order in which they appear on the route.
+    /// The route must contain at least two nodes.
+    pub fn new(id: u32, route: Vec<u32>, stops: Vec<TransitStop>, departures: Departures) -> Self {
+        assert!(route.len() >= 2, "A transit route needs at least two nodes");
+        departures.assert_valid();
+
+        // A stop that can't be found further along the route would never be reached
+        let mut route_position = 0;
+        for stop in stops.iter() {
+            let offset = route[route_position..]
+                .iter()
+                .position(|node| *node == stop.node)
+                .expect("Transit stops must be on the route, in the order of the route");
+            route_position += offset + 1;
+        }
+
+        Self {
+            id,
+            route,
+            stops,
+            departures,
+            vehicle_class: VehicleClass::Bus,
+        }
+    }
+
+    pub fn with_vehicle_class(mut self, vehicle_class: VehicleClass) -> Self {
+        self.vehicle_class = vehicle_class;
+        self
+    }
+
+    pub fn id(&self) -> u32 {
+        self.id
+    }
+
+    pub fn route(&self) -> &[u32] {
+        self.route.as_ref()
+    }
+
+    pub fn stops(&self) -> &[TransitStop] {
+        self.stops.as_ref()
+    }
+
+    pub fn departures(&self) -> &Departures {
+        &self.departures
+    }
+
+    pub fn vehicle_class(&self) -> VehicleClass {
+        self.vehicle_class
+    }
+
+    pub(crate) fn spawn_vehicle(
+        &self,
+        road_user_id: u32,
+        departure_time: f32,
+        network: &RoadNetwork,
+    ) -> RoadUser {
+        let mut trip = TransitTrip {
+            line: self.id,
+            departure_time,
+            stops: self.stops.clone(),
+            next_stop: 0,
+            arrival_time: None,
+            dwell_remaining: 0.0,
+        };
+
+        // A stop at the first node of the route is served before the vehicle leaves
+        if trip.next_stop().map(|stop| stop.node) == Some(self.route[0]) {
+            trip.begin_dwell(departure_time);
+        }
+
+        RoadUser::new(
+            road_user_id,
+            network.find_node(self.route[0]).location(),
+            0.0,
+            self.vehicle_class.default_acceleration(),
+            self.vehicle_class.default_deceleration(),
+            self.vehicle_class.default_max_steering_angle(),
+            self.route[1],
+            *self.route.last().unwrap(),
+            network,
+        )
+        .with_class(self.vehicle_class)
+        .with_fixed_route(self.route[1..].to_vec())
+        .with_transit_trip(trip)
+    }
+}
+
+/// The progress of a single vehicle along its transit line
+#[derive(Debug, Clone)]
+pub struct TransitTrip {
+    line: u32,
+    departure_time: f32,
+    stops: Vec<TransitStop>,
+    next_stop: usize,
+    arrival_time: Option<f32>, // Some while dwelling at the next stop
+    dwell_remaining: f32,
+}
+
+impl TransitTrip {
+    pub fn line(&self) -> u32 {
+        self.line
+    }
+
+    pub fn departure_time(&self) -> f32 {
+        self.departure_time
+    }
+
+    pub fn next_stop(&self) -> Option<&TransitStop> {
+        self.stops.get(self.next_stop)
+    }
+
+    pub fn is_dwelling(&self) -> bool {
+        self.arrival_time.is_some()
+    }
+
+    /// Whether a vehicle on this trip stands in the lane for other traffic
+    pub fn is_blocking_lane(&self) -> bool {
+        self.is_dwelling()
+            && !self
+                .next_stop()
+                .map(TransitStop::has_bay)
+                .unwrap_or_default()
+    }
+
+    pub(crate) fn begin_dwell(&mut self, current_time: f32) {
+        let Some(stop) = self.next_stop() else {
+            return;
+        };
+
+        self.dwell_remaining = stop.dwell_time;
+        self.arrival_time = Some(current_time);
+    }
+
+    /// Progresses the dwell time. Returns the visit when the vehicle is done at the stop.
+    pub(crate) fn dwell(
+        &mut self,
+        road_user: u32,
+        current_time: f32,
+        delta_time: f32,
+    ) -> Option<StopVisit> {
+        let arrival_time = self.arrival_time?;
+
+        self.dwell_remaining -= delta_time;
+        if self.dwell_remaining > 0.0 {
+            return None;
+        }
+
+        let stop = self.next_stop()?;
+        let visit = StopVisit {
+            road_user,
+            line: self.line,
+            trip_departure: self.departure_time,
+            stop_index: self.next_stop,
+            node: stop.node,
+            scheduled_arrival: self.departure_time + stop.scheduled_offset,
+            arrival_time,
+            departure_time: current_time + delta_time,
+        };
+
+        self.arrival_time = None;
+        self.next_stop += 1;
+
+        Some(visit)
+    }
+}
+
+/// A record of a transit vehicle serving a stop
+#[derive(Debug, Clone, PartialEq)]
+pub struct StopVisit {
+    pub road_user: u32,
+    pub line: u32,
+    pub trip_departure: f32,
+    pub stop_index: usize,
+    pub node: u32,
+    pub scheduled_arrival: f32,
+    pub arrival_time: f32,
+    pub departure_time: f32,
+}
+
+impl StopVisit {
+    /// Positive when the vehicle was late, negative when it was early
+    pub fn schedule_deviation(&self) -> f32 {
+        self.arrival_time - self.scheduled_arrival
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ScheduleAdherence {
+    pub visits: usize,
+    pub mean_deviation: f32,
+    pub max_lateness: f32,
+    pub max_earliness: f32,
+    /// The fraction of the visits within the on-time window
+    pub on_time_fraction: f32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Bunching {
+    pub headways: usize,
+    /// The amount of headways that were shorter than the threshold fraction of the scheduled headway
+    pub bunched: usize,
+    /// The coefficient of variation of the actual headways divided by the scheduled ones
+    pub headway_variation: f32,
+}
+
+/// All stop visits that happened during a simulation
+#[derive(Debug, Clone, Default)]
+pub struct TransitReport {
+    visits: Vec<StopVisit>,
+}
+
+impl TransitReport {
+    pub(crate) fn record(&mut self, visit: StopVisit) {
+        self.visits.push(visit);
+    }
+
+    /// Forgets the visits that ended after the time
+    pub(crate) fn truncate(&mut self, time: f32) {
+        self.visits.retain(|visit| visit.departure_time <= time);
+    }
+
+    pub fn visits(&self) -> &[StopVisit] {
+        self.visits.as_ref()
+    }
+
+    /// A visit is counted as on time when it arrived no more than `early_tolerance` seconds
+    /// before and no more than `late_tolerance` seconds after the schedule
+    pub fn schedule_adherence(
+        &self,
+        line: u32,
+        early_tolerance: f32,
+        late_tolerance: f32,
+    ) -> Option<ScheduleAdherence> {
+        let deviations = self
+            .visits
+            .iter()
+            .filter(|visit| visit.line == line)
+            .map(StopVisit::schedule_deviation)
+            .collect::<Vec<_>>();
+
+        if deviations.is_empty() {
+            return None;
+        }
+
+        let on_time = deviations
+            .iter()
+            .filter(|deviation| (-early_tolerance..=late_tolerance).contains(*deviation))
+            .count();
+
+        Some(ScheduleAdherence {
+            visits: deviations.len(),
+            mean_deviation: deviations.iter().sum::<f32>() / deviations.len() as f32,
+            max_lateness: deviations.iter().copied().fold(0.0, f32::max),
+            max_earliness: -deviations.iter().copied().fold(0.0, f32::min),
+            on_time_fraction: on_time as f32 / deviations.len() as f32,
+        })
+    }
+
+    /// Compares the headways between consecutive vehicles at every stop of the line with the
+    /// scheduled headways. A headway below `threshold` times the scheduled one counts as bunched.
+    pub fn bunching(&self, line: u32, threshold: f32) -> Option<Bunching> {
+        let mut visits = self
+            .visits
+            .iter()
+            .filter(|visit| visit.line == line)
+            .collect::<Vec<_>>();
+        visits.sort_by(|a, b| {
+            a.stop_index
+                .cmp(&b.stop_index)
+                .then(a.arrival_time.total_cmp(&b.arrival_time))
+        });
+
+        let headway_ratios = visits
+            .windows(2)
+            .filter(|pair| pair[0].stop_index == pair[1].stop_index)
+            .filter_map(|pair| {
+                let scheduled = (pair[1].scheduled_arrival - pair[0].scheduled_arrival).abs();
+                let actual = pair[1].arrival_time - pair[0].arrival_time;
+                (scheduled > 0.0).then_some(actual / scheduled)
+            })
+            .collect::<Vec<_>>();
+
+        if headway_ratios.is_empty() {
+            return None;
+        }
+
+        let mean = headway_ratios.iter().sum::<f32>() / headway_ratios.len() as f32;
+        let variance = headway_ratios
+            .iter()
+            .map(|ratio| (ratio - mean).powi(2))
+            .sum::<f32>()
+            / headway_ratios.len() as f32;
+
+        Some(Bunching {
+            headways: headway_ratios.len(),
+            bunched: headway_ratios
+                .iter()
+                .filter(|ratio| **ratio < threshold)
+                .count(),
+            headway_variation: variance.sqrt() / mean,
+        })
+    }
+}
@@ -1,30 +1,114 @@
-use std::f32::consts::PI;
+use std::{
+    collections::{HashMap, VecDeque},
+    f32::consts::PI,
+};
 
 use nalgebra::{Point3, Rotation2, Vector3};
 use ordered_float::OrderedFloat;
 
 use crate::{
-    road::RoadNetwork,
+    battery::Battery,
+    driver::DriverBehaviour,
+    event::SimulationEvent,
+    incident::RoadClosures,
+    parking::TripLeg,
+    perception::{Leader, Observation, PerceptionBuffer},
+    road::{Node, RoadNetwork},
     traffic_light::{TrafficLight, TrafficLightState},
+    transit::TransitTrip,
+    vehicle::{VehicleClass, GRAVITY},
+    weather::Weather,
 };
 
-#[derive(Debug)]
+const LEADER_LOOKAHEAD: f32 = 150.0; // m
+const MINIMUM_GAP: f32 = 2.0; // m
+/// Below this speed the engine power isn't what limits the acceleration
+const MIN_POWER_LIMITED_SPEED: f32 = 1.0; // m/s
+/// Even on the steepest descent the brakes keep working a little
+const MIN_DECELERATION: f32 = 0.5; // m/s/s
+/// How far from a closed node we stop when we're already on the edge leading into it
+const CLOSURE_CLEARANCE: f32 = 5.0; // m
+
+/// Everything a road user can observe about the world during a tick
+pub struct TickContext<'a> {
+    pub network: &'a RoadNetwork,
+    pub traffic_lights: &'a [Box<dyn TrafficLight>],
+    pub road_users: &'a [RoadUserSnapshot],
+    /// The limits that currently replace the max speed of the nodes
+    pub speed_limits: &'a HashMap<u32, f32>,
+    pub closures: &'a RoadClosures,
+    pub weather: &'a Weather,
+    pub current_time: f32,
+    pub delta_time: f32,
+}
+
+impl TickContext<'_> {
+    /// The speed limit that currently applies on the node
+    pub fn speed_limit(&self, node: &Node) -> f32 {
+        self.speed_limits
+            .get(&node.id)
+            .copied()
+            .unwrap_or_else(|| node.max_speed())
+    }
+}
+
+/// The state of a road user at the start of a tick, as seen by the other road users
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RoadUserSnapshot {
+    pub id: u32,
+    pub location: Point3<f32>,
+    pub speed: f32,
+    pub length: f32,
+    pub next_node: u32,
+    pub distance_to_next_node: f32,
+    /// False if the road user can be passed, e.g. when it's dwelling in a bus bay
+    pub blocks_lane: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TickOutcome {
+    Driving,
+    ReachedDestination,
+    NoPathFound,
+}
+
+#[derive(Debug, Clone)]
 pub struct RoadUser {
     pub id: u32,
 
     location: Point3<f32>,
     current_direction: Vector3<f32>,
     current_speed: f32,
+    current_acceleration: f32,
+
+    class: VehicleClass,
+    length: f32, // m
+    width: f32,  // m
 
     acceleration: f32,       // m/s/s
     deceleration: f32,       // m/s/s
     max_steering_angle: f32, // rads/s
 
+    driver_behaviour: Option<DriverBehaviour>,
+    perception: PerceptionBuffer,
+
+    previous_node: Option<u32>,
     next_nodes: Vec<u32>,
     destination_node: u32,
+    fixed_route: bool,
+    trip_chain: VecDeque<TripLeg>, // The front is the leg we're currently on
+
+    transit_trip: Option<TransitTrip>,
+    battery: Option<Battery>,
+    /// The destination we'll continue to after charging
+    charging_detour: Option<u32>,
+    /// Set when we couldn't reach any charger, so we don't search again every tick
+    no_charger_reachable: bool,
+    halted: bool,
 }
 
 impl RoadUser {
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         id: u32,
         location: Point3<f32>,
@@ -36,31 +120,206 @@ impl RoadUser {
         destination_node: u32,
         network: &RoadNetwork,
     ) -> Self {
+        let class = VehicleClass::default();
+
         Self {
             id,
             location,
             current_direction: (network.find_node(first_node).location() - location).normalize(),
             current_speed,
+            current_acceleration: 0.0,
+            class,
+            length: class.default_length(),
+            width: class.default_width(),
             acceleration,
             deceleration,
             max_steering_angle,
+            driver_behaviour: None,
+            perception: PerceptionBuffer::default(),
+            previous_node: None,
             next_nodes: vec![first_node],
             destination_node,
+            fixed_route: false,
+            trip_chain: VecDeque::new(),
+            transit_trip: None,
+            battery: class
+                .default_battery_capacity()
+                .map(|capacity| Battery::new(capacity, 1.0)),
+            charging_detour: None,
+            no_charger_reachable: false,
+            halted: false,
+        }
+    }
+
+    /// A road user of the class with its default properties that departs standing still from
+    /// the origin. None if the destination can't be reached.
+    pub(crate) fn departing_from(
+        id: u32,
+        class: VehicleClass,
+        origin: u32,
+        destination: u32,
+        network: &RoadNetwork,
+        closures: &RoadClosures,
+    ) -> Option<Self> {
+        let origin_node = network.find_node(origin);
+        let first_node = origin_node.next_nodes(network).next()?;
+
+        let mut user = Self::new(
+            id,
+            origin_node.location(),
+            0.0,
+            class.default_acceleration(),
+            class.default_deceleration(),
+            class.default_max_steering_angle(),
+            first_node.id,
+            destination,
+            network,
+        )
+        .with_class(class);
+
+        user.depart_from(origin, network, closures).then_some(user)
+    }
+
+    /// Sets the class and takes over its default dimensions and battery
+    pub fn with_class(mut self, class: VehicleClass) -> Self {
+        self.class = class;
+        self.length = class.default_length();
+        self.width = class.default_width();
+        self.battery = class
+            .default_battery_capacity()
+            .map(|capacity| Battery::new(capacity, 1.0));
+        self
+    }
+
+    pub fn with_dimensions(mut self, length: f32, width: f32) -> Self {
+        self.length = length;
+        self.width = width;
+        self
+    }
+
+    /// The maximum acceleration and comfortable deceleration of the vehicle in m/s/s
+    pub fn with_acceleration(mut self, acceleration: f32, deceleration: f32) -> Self {
+        self.acceleration = acceleration;
+        self.deceleration = deceleration;
+        self
+    }
+
+    /// Follow the given nodes instead of pathfinding to the destination.
+    /// The route must end at the destination node.
+    pub fn with_fixed_route(mut self, route: Vec<u32>) -> Self {
+        self.next_nodes = route;
+        self.fixed_route = true;
+        self
+    }
+
+    pub fn with_driver_behaviour(mut self, driver_behaviour: DriverBehaviour) -> Self {
+        self.driver_behaviour = Some(driver_behaviour);
+        self
+    }
+
+    /// Drive the legs one after the other, parking at the end of each leg.
+    /// This replaces the destination node with the destination of the first leg.
+    pub fn with_trip_chain(mut self, trip_chain: Vec<TripLeg>) -> Self {
+        self.trip_chain = trip_chain.into();
+
+        if let Some(first_leg) = self.trip_chain.front() {
+            self.destination_node = first_leg.destination;
+        }
+
+        self
+    }
+
+    pub fn with_transit_trip(mut self, transit_trip: TransitTrip) -> Self {
+        self.transit_trip = Some(transit_trip);
+        self
+    }
+
+    /// Drive electrically. Road users with a battery don't have tailpipe emissions.
+    pub fn with_battery(mut self, battery: Battery) -> Self {
+        self.battery = Some(battery);
+        self
+    }
+
+    pub fn snapshot(&self, network: &RoadNetwork) -> RoadUserSnapshot {
+        RoadUserSnapshot {
+            id: self.id,
+            location: self.location,
+            speed: self.current_speed,
+            length: self.length,
+            next_node: self.next_nodes[0],
+            distance_to_next_node: (self.location
+                - network.find_node(self.next_nodes[0]).location())
+            .magnitude(),
+            blocks_lane: !self
+                .transit_trip
+                .as_ref()
+                .is_some_and(|trip| trip.is_dwelling() && !trip.is_blocking_lane()),
         }
     }
 
     pub fn tick(
         &mut self,
-        network: &RoadNetwork,
-        traffic_lights: &[Box<dyn TrafficLight>],
-        delta_time: f32,
-    ) -> bool {
+        context: &TickContext,
+        events: &mut Vec<SimulationEvent>,
+    ) -> TickOutcome {
+        if self.halted {
+            self.current_speed = 0.0;
+            self.current_acceleration = 0.0;
+            return TickOutcome::Driving;
+        }
+
+        let previous_speed = self.current_speed;
+
+        let network = context.network;
+        if !self.fixed_route {
+            self.change_lane_around_closure(network, context.closures);
+        }
+
+        let delta_time = context.delta_time;
+        let driver = self.driver_behaviour();
+
         let next_node = network.find_node(self.next_nodes[0]);
         let second_next_node = self.next_nodes.get(1).map(|id| network.find_node(*id));
 
-        let mut target_speed = next_node.max_speed();
+        let observation = Observation {
+            time: context.current_time,
+            own_speed: self.current_speed,
+            leader: self.find_leader(context),
+            traffic_lights: self
+                .next_nodes
+                .iter()
+                .filter_map(|node| {
+                    context
+                        .traffic_lights
+                        .iter()
+                        .find(|light| light.node() == *node)
+                })
+                .map(|light| (light.node(), light.get_state()))
+                .collect(),
+            speed_limit: context.speed_limit(next_node),
+        };
+        let perceived = self
+            .perception
+            .observe(observation, driver.reaction_time)
+            .clone();
+
+        let conditions = context
+            .weather
+            .condition_at(&self.location, context.current_time)
+            .effects();
+
+        // The part of gravity that pulls us back along the slope (negative when going downhill)
+        let gravity_along_slope = GRAVITY * self.current_direction.z;
+        let deceleration = (self.deceleration * conditions.deceleration_factor
+            + gravity_along_slope)
+            .max(MIN_DECELERATION);
+
+        let mut target_speed =
+            perceived.speed_limit * driver.desired_speed_factor * conditions.desired_speed_factor;
 
-        let target_direction = (self.location - next_node.location()).normalize();
+        let target_direction = (next_node.location() - self.location)
+            .try_normalize(f32::EPSILON)
+            .unwrap_or(self.current_direction);
 
         'corner_speed: {
             if let Some(second_next_node) = second_next_node {
@@ -71,20 +330,22 @@ impl RoadUser {
                     &target_direction.xy(),
                     &next_target_direction.xy(),
                 )
-                .angle();
+                .angle()
+                .abs();
 
                 if expected_angle < PI / 10000.0 {
                     break 'corner_speed;
                 }
 
                 let min_seconds_required = expected_angle / self.max_steering_angle;
-                let max_corner_speed = next_target_distance / min_seconds_required;
+                let max_corner_speed =
+                    next_target_distance / min_seconds_required * conditions.corner_speed_factor;
 
-                let current_speed_difference_too_fast = max_corner_speed - self.current_speed;
+                let current_speed_difference_too_fast = self.current_speed - max_corner_speed;
 
                 if current_speed_difference_too_fast > 0.0 {
                     let breaking_time_required =
-                        current_speed_difference_too_fast / (self.deceleration / 2.0);
+                        current_speed_difference_too_fast / (deceleration / 2.0);
                     let current_speed_breaking_distance_required =
                         self.current_speed * breaking_time_required;
 
@@ -99,51 +360,118 @@ impl RoadUser {
 
         let is_stopping_for_traffic_light = 'traffic_light_speed: {
             let Some(first_next_traffic_light) = self.next_nodes.iter().find_map(|node| {
-                            traffic_lights
-                                .iter()
-                                .find_map(|light| (light.node() == *node).then_some(light))
-                        }) else {
+                context
+                    .traffic_lights
+                    .iter()
+                    .find(|light| light.node() == *node)
+            }) else {
                 break 'traffic_light_speed false;
             };
 
-            if first_next_traffic_light.get_state() == TrafficLightState::Green {
+            let perceived_state = perceived
+                .traffic_light_state(first_next_traffic_light.node())
+                .unwrap_or_else(|| first_next_traffic_light.get_state());
+
+            if perceived_state == TrafficLightState::Green {
                 break 'traffic_light_speed false;
             }
 
-            let distance_to_traffic_light =
-                ((self.location - network.find_node(self.next_nodes[0]).location()).magnitude()
-                    + self
-                        .next_nodes
-                        .windows(2)
-                        .take_while(|next_nodes| next_nodes[1] == first_next_traffic_light.node())
-                        .map(|next_nodes| {
-                            network
-                                .find_node(next_nodes[0])
-                                .distance_to(network.find_node(next_nodes[1]))
-                        })
-                        .sum::<f32>() - 0.1).max(0.0);
-
-            let time_desired_to_break = self.current_speed / (self.deceleration / 1.5);
+            let distance_to_traffic_light = (self
+                .distance_along_path(network, first_next_traffic_light.node())
+                .unwrap_or_default()
+                - 0.1)
+                .max(0.0);
+
+            let time_desired_to_break = self.current_speed / (deceleration / 1.5);
             let distance_desired_to_break = self.current_speed / 2.0 * time_desired_to_break;
 
-            let time_required_to_break = self.current_speed / self.deceleration;
+            let time_required_to_break =
+                self.current_speed / (deceleration * driver.orange_deceleration_factor);
             let distance_required_to_break = self.current_speed / 2.0 * time_required_to_break;
 
             if distance_to_traffic_light < distance_required_to_break
-                && first_next_traffic_light.get_state() == TrafficLightState::Orange
+                && perceived_state == TrafficLightState::Orange
             {
                 break 'traffic_light_speed false;
             }
 
-            if distance_to_traffic_light < distance_desired_to_break {
+            // Standing at the stop line both distances are zero, we have to stay there
+            if distance_to_traffic_light <= distance_desired_to_break {
                 target_speed = 0.0;
             }
 
-            true
+            first_next_traffic_light.node() == next_node.id
         };
 
+        if let Some(trip) = self.transit_trip.as_mut() {
+            if let Some(visit) = trip.dwell(self.id, context.current_time, delta_time) {
+                events.push(SimulationEvent::TransitStopServed(visit));
+            }
+        }
+
+        let is_serving_transit_stop = 'transit_stop: {
+            let Some(trip) = self.transit_trip.as_ref() else {
+                break 'transit_stop false;
+            };
+
+            if trip.is_dwelling() {
+                target_speed = 0.0;
+                break 'transit_stop true;
+            }
+
+            let Some(stop_node) = trip.next_stop().map(|stop| stop.node()) else {
+                break 'transit_stop false;
+            };
+            let Some(distance_to_stop) = self.distance_along_path(network, stop_node) else {
+                break 'transit_stop false;
+            };
+
+            if distance_to_stop < 0.5 && self.current_speed < 0.5 {
+                target_speed = 0.0;
+                self.transit_trip
+                    .as_mut()
+                    .unwrap()
+                    .begin_dwell(context.current_time);
+                break 'transit_stop true;
+            }
+
+            // Follow the braking curve so we come to a standstill right at the stop
+            target_speed = target_speed.min((2.0 * (deceleration / 1.5) * distance_to_stop).sqrt());
+
+            stop_node == next_node.id
+        };
+
+        let is_stopping_for_closure = 'closure: {
+            let Some(index) = self.first_closure_on_path(network, context.closures) else {
+                break 'closure false;
+            };
+
+            let distance_to_stop = if index == 0 {
+                // We're already driving towards the closed node
+                (self.location - next_node.location()).magnitude() - CLOSURE_CLEARANCE
+            } else {
+                self.distance_along_path(network, self.next_nodes[index - 1])
+                    .unwrap_or_default()
+                    - 0.1
+            }
+            .max(0.0);
+
+            target_speed = target_speed.min((2.0 * (deceleration / 1.5) * distance_to_stop).sqrt());
+
+            index == 1
+        };
+
+        if let Some(leader) = perceived.extrapolated_leader(context.current_time) {
+            let free_gap = (leader.gap - MINIMUM_GAP).max(0.0);
+            let braking_speed =
+                (leader.speed.powi(2) + 2.0 * (deceleration / 1.5) * free_gap).sqrt();
+            let headway_speed = free_gap / (driver.time_headway * conditions.headway_factor);
+
+            target_speed = target_speed.min(braking_speed).min(headway_speed);
+        }
+
         let total_rotation =
-            Rotation2::rotation_between(&target_direction.xy(), &self.current_direction.xy());
+            Rotation2::rotation_between(&self.current_direction.xy(), &target_direction.xy());
 
         let total_rotation_angle = total_rotation.angle();
         let max_steering_angle = self.max_steering_angle * delta_time;
@@ -159,53 +487,352 @@ impl RoadUser {
         self.current_direction.z = target_direction.z;
         self.current_direction = self.current_direction.normalize();
 
+        let power_limited_acceleration = self.class.power()
+            / self.class.mass()
+            / self.current_speed.max(MIN_POWER_LIMITED_SPEED);
+        // The driver makes up for the slope with extra throttle until the engine runs out of
+        // power, so the grade only slows down climbs that are power limited. Downhill the driver
+        // doesn't accelerate harder than usual and brakes to stay below the target speed.
+        let acceleration = (self.acceleration * driver.acceleration_factor)
+            .min(power_limited_acceleration - gravity_along_slope);
+
         let speed_difference = target_speed - self.current_speed;
         if self.current_speed < target_speed {
-            self.current_speed += (self.acceleration * delta_time).min(speed_difference).max(0.0);
+            // On a steep climb the acceleration can be negative and we lose speed
+            self.current_speed += (acceleration * delta_time).min(speed_difference);
         } else if self.current_speed > target_speed {
-            self.current_speed -= (self.deceleration * delta_time).max(speed_difference).max(0.0);
+            self.current_speed -= (deceleration * delta_time).min(-speed_difference);
         }
-
-        self.location += self.current_direction * self.current_speed * delta_time;
-
-        if !is_stopping_for_traffic_light && (self.location - next_node.location()).magnitude() < 0.5 {
+        self.current_speed = self.current_speed.max(0.0);
+        self.current_acceleration = (self.current_speed - previous_speed) / delta_time;
+
+        let step = self.current_speed * delta_time;
+        self.location += self.current_direction * step;
+
+        // With long steps we could jump over the node, so the reach grows with the step size
+        if !is_stopping_for_traffic_light
+            && !is_serving_transit_stop
+            && !is_stopping_for_closure
+            && (self.location - next_node.location()).magnitude() < step.max(0.5)
+        {
             if self.next_nodes.first() == Some(&self.destination_node) {
                 println!("Reached destination");
-                return false;
+                return TickOutcome::ReachedDestination;
             }
 
-            self.recalculate_path(network);
+            self.previous_node = Some(next_node.id);
+
+            if self.fixed_route {
+                self.next_nodes.remove(0);
+            } else {
+                self.recalculate_path(network, context.closures);
+            }
 
             if self.next_nodes.is_empty() {
                 println!("Could not find a path");
-                return false;
+                return TickOutcome::NoPathFound;
             };
         }
 
+        TickOutcome::Driving
+    }
+
+    /// Changes the destination of the current leg while standing at a node, e.g. to search
+    /// for another parking spot. Returns false and keeps the old destination if the new one
+    /// can't be reached.
+    pub(crate) fn redirect(
+        &mut self,
+        destination: u32,
+        network: &RoadNetwork,
+        closures: &RoadClosures,
+    ) -> bool {
+        let previous_destination = self.destination_node;
+        let previous_nodes = self.next_nodes.clone();
+
+        self.set_leg_destination(destination);
+        self.recalculate_path(network, closures);
+
+        if self.next_nodes.is_empty() {
+            self.set_leg_destination(previous_destination);
+            self.next_nodes = previous_nodes;
+            return false;
+        }
+
         true
     }
 
-    fn recalculate_path(&mut self, network: &RoadNetwork) {
+    fn set_leg_destination(&mut self, destination: u32) {
+        self.destination_node = destination;
+        if let Some(leg) = self.trip_chain.front_mut() {
+            leg.destination = destination;
+        }
+    }
+
+    /// Finishes the current leg and puts the road user back on the network at the given node
+    /// for the next one. Returns false if there's no next leg or it can't be reached.
+    pub(crate) fn depart_for_next_leg(
+        &mut self,
+        node: u32,
+        network: &RoadNetwork,
+        closures: &RoadClosures,
+    ) -> bool {
+        self.trip_chain.pop_front();
+        let Some(next_leg) = self.trip_chain.front() else {
+            return false;
+        };
+
+        self.destination_node = next_leg.destination;
+        self.depart_from(node, network, closures)
+    }
+
+    /// True if the battery is low and we're not on our way to a charger yet
+    pub(crate) fn needs_charging(&self) -> bool {
+        !self.fixed_route
+            && !self.halted
+            && self.charging_detour.is_none()
+            && !self.no_charger_reachable
+            && self.battery.is_some_and(|battery| battery.is_low())
+    }
+
+    /// Drives to the charger first and continues to the destination after charging.
+    /// Returns false if the charger can't be reached.
+    pub(crate) fn detour_to_charger(
+        &mut self,
+        charger_node: u32,
+        network: &RoadNetwork,
+        closures: &RoadClosures,
+    ) -> bool {
+        let destination = self.destination_node;
+        self.destination_node = charger_node;
+        self.reroute(network, closures);
+
+        if self.next_nodes.len() < 2 && self.next_nodes.first() != Some(&charger_node) {
+            self.destination_node = destination;
+            self.reroute(network, closures);
+            return false;
+        }
+
+        self.charging_detour = Some(destination);
+        true
+    }
+
+    /// Stops searching for a charger until `search_charger_again` is called
+    pub(crate) fn give_up_charger_search(&mut self) {
+        self.no_charger_reachable = true;
+    }
+
+    /// A charger might be reachable now, e.g. because a road reopened or a station was added
+    pub(crate) fn search_charger_again(&mut self) {
+        self.no_charger_reachable = false;
+    }
+
+    pub(crate) fn is_on_charging_detour(&self) -> bool {
+        self.charging_detour.is_some()
+    }
+
+    /// Continues to the original destination from the charger at the node
+    pub(crate) fn depart_after_charging(
+        &mut self,
+        node: u32,
+        network: &RoadNetwork,
+        closures: &RoadClosures,
+    ) -> bool {
+        let Some(destination) = self.charging_detour.take() else {
+            return false;
+        };
+
+        self.destination_node = destination;
+        self.depart_from(node, network, closures)
+    }
+
+    /// Re-enters the network at the node, standing still
+    fn depart_from(&mut self, node: u32, network: &RoadNetwork, closures: &RoadClosures) -> bool {
+        self.location = network.find_node(node).location();
+        self.current_speed = 0.0;
+        self.previous_node = Some(node);
+        self.next_nodes = vec![node];
+        self.fixed_route = false;
+        self.recalculate_path(network, closures);
+
+        let Some(first_node) = self.next_nodes.first() else {
+            return false;
+        };
+
+        self.current_direction = (network.find_node(*first_node).location() - self.location)
+            .try_normalize(f32::EPSILON)
+            .unwrap_or(self.current_direction);
+
+        true
+    }
+
+    /// Finds a new path around the closures, or back to the shortest one after a reopening.
+    /// Road users on a fixed route keep it and wait for the road to reopen.
+    pub(crate) fn avoid_closures(&mut self, network: &RoadNetwork, closures: &RoadClosures) {
+        if !self.fixed_route {
+            self.reroute(network, closures);
+        }
+    }
+
+    /// Finds a new path to the destination while keeping the node we're driving to
+    fn reroute(&mut self, network: &RoadNetwork, closures: &RoadClosures) {
+        let next_node = self.next_nodes[0];
+        if next_node == self.destination_node {
+            self.next_nodes = vec![next_node];
+            return;
+        }
+
+        self.recalculate_path(network, closures);
+        self.next_nodes.insert(0, next_node);
+    }
+
+    /// Moves over to the lane next to the node we're driving to if that node is closed
+    fn change_lane_around_closure(&mut self, network: &RoadNetwork, closures: &RoadClosures) {
+        if !closures.is_node_closed(self.next_nodes[0]) {
+            return;
+        }
+
+        let Some(adjacent_node) = self.open_adjacent_node(network, closures, self.next_nodes[0])
+        else {
+            return;
+        };
+
+        self.next_nodes = vec![adjacent_node];
+        self.reroute(network, closures);
+    }
+
+    /// A node in a lane next to the given one that isn't closed
+    fn open_adjacent_node(
+        &self,
+        network: &RoadNetwork,
+        closures: &RoadClosures,
+        node: u32,
+    ) -> Option<u32> {
+        let node = network.find_node(node);
+        [
+            node.adjacent_node_left(network),
+            node.adjacent_node_right(network),
+        ]
+        .into_iter()
+        .flatten()
+        .map(|adjacent_node| adjacent_node.id)
+        .find(|adjacent_node| !closures.is_node_closed(*adjacent_node))
+    }
+
+    /// The index in the next nodes of the first closed node or the end of the first closed
+    /// edge we'd have to stop for. Closed nodes we can change lanes around don't count.
+    fn first_closure_on_path(
+        &self,
+        network: &RoadNetwork,
+        closures: &RoadClosures,
+    ) -> Option<usize> {
+        if closures.is_empty() {
+            return None;
+        }
+
+        let previous_nodes =
+            std::iter::once(self.previous_node).chain(self.next_nodes.iter().copied().map(Some));
+
+        previous_nodes
+            .zip(self.next_nodes.iter().copied())
+            .position(|(from, to)| {
+                let is_closed = match from {
+                    Some(from) => closures.is_edge_closed(from, to),
+                    None => closures.is_node_closed(to),
+                };
+                let can_change_lane = !self.fixed_route
+                    && closures.is_node_closed(to)
+                    && self.open_adjacent_node(network, closures, to).is_some();
+
+                is_closed && !can_change_lane
+            })
+    }
+
+    /// Finds a path around the closures. If the closures cut us off from the destination,
+    /// we take the path through them and wait in front of them until they reopen.
+    fn recalculate_path(&mut self, network: &RoadNetwork, closures: &RoadClosures) {
+        let mut next_path = self
+            .find_path(network, closures)
+            .or_else(|| self.find_path(network, &RoadClosures::default()))
+            .unwrap_or_default();
+
+        if !next_path.is_empty() {
+            next_path.remove(0);
+        }
+
+        self.next_nodes = next_path;
+    }
+
+    fn find_path(&self, network: &RoadNetwork, closures: &RoadClosures) -> Option<Vec<u32>> {
         let current_node = network.find_node(*self.next_nodes.first().unwrap());
         let destination_node = network.find_node(self.destination_node);
 
-        let (mut next_path, _) = pathfinding::directed::astar::astar(
+        let (path, _) = pathfinding::directed::astar::astar(
             &current_node,
             |test_node| {
+                let from = test_node.id;
                 test_node
                     .next_nodes(network)
+                    .filter(move |next_node| !closures.is_edge_closed(from, next_node.id))
                     .map(|next_node| (next_node, OrderedFloat(current_node.distance_to(next_node))))
             },
             |test_node| OrderedFloat(test_node.distance_to(destination_node)),
             |test_node| *test_node == destination_node,
-        )
-        .unwrap_or_default();
+        )?;
 
-        if !next_path.is_empty() {
-            next_path.remove(0);
-        }
+        Some(path.into_iter().map(|node| node.id).collect())
+    }
 
-        self.next_nodes = next_path.into_iter().map(|node| node.id).collect();
+    /// The nodes we're going to pass with the distance we need to travel to get there
+    fn path_distances<'s>(
+        &'s self,
+        network: &'s RoadNetwork,
+    ) -> impl Iterator<Item = (u32, f32)> + 's {
+        let first_node = self.next_nodes[0];
+        let first_distance = (self.location - network.find_node(first_node).location()).magnitude();
+
+        std::iter::once((first_node, first_distance)).chain(self.next_nodes.windows(2).scan(
+            first_distance,
+            move |distance, nodes| {
+                *distance += network
+                    .find_node(nodes[0])
+                    .distance_to(network.find_node(nodes[1]));
+                Some((nodes[1], *distance))
+            },
+        ))
+    }
+
+    /// The distance we need to travel to reach the given node, if it's on our path
+    fn distance_along_path(&self, network: &RoadNetwork, node: u32) -> Option<f32> {
+        self.path_distances(network)
+            .find(|(id, _)| *id == node)
+            .map(|(_, distance)| distance)
+    }
+
+    /// Finds the closest road user in front of us on our path
+    fn find_leader(&self, context: &TickContext) -> Option<Leader> {
+        // Every edge that starts within the lookahead can have a leader on it
+        let mut edge_start = 0.0;
+        self.path_distances(context.network)
+            .take_while(move |(_, distance)| {
+                let is_within_lookahead = edge_start < LEADER_LOOKAHEAD;
+                edge_start = *distance;
+                is_within_lookahead
+            })
+            .flat_map(|(node, distance)| {
+                context
+                    .road_users
+                    .iter()
+                    .filter(move |other| {
+                        other.id != self.id && other.blocks_lane && other.next_node == node
+                    })
+                    .map(move |other| (distance - other.distance_to_next_node, other))
+            })
+            .filter(|(center_distance, _)| (0.0..LEADER_LOOKAHEAD).contains(center_distance))
+            .map(|(center_distance, other)| Leader {
+                gap: center_distance - (self.length + other.length) / 2.0,
+                speed: other.speed,
+            })
+            .min_by(|a, b| a.gap.total_cmp(&b.gap))
     }
 
     pub fn location(&self) -> Point3<f32> {
@@ -216,7 +843,81 @@ impl RoadUser {
         self.current_direction
     }
 
+    /// Degrees clockwise from the positive y axis (north), like a compass
+    pub fn heading(&self) -> f32 {
+        self.current_direction
+            .x
+            .atan2(self.current_direction.y)
+            .to_degrees()
+            .rem_euclid(360.0)
+    }
+
     pub fn current_speed(&self) -> f32 {
         self.current_speed
     }
+
+    pub fn destination_node(&self) -> u32 {
+        self.destination_node
+    }
+
+    pub fn current_trip_leg(&self) -> Option<&TripLeg> {
+        self.trip_chain.front()
+    }
+
+    /// m/s/s, negative when braking
+    pub fn current_acceleration(&self) -> f32 {
+        self.current_acceleration
+    }
+
+    pub fn next_node(&self) -> u32 {
+        self.next_nodes[0]
+    }
+
+    /// The edge we're driving on as `(from, to)`. None before we've passed the first node.
+    pub fn current_edge(&self) -> Option<(u32, u32)> {
+        self.previous_node
+            .map(|previous| (previous, self.next_nodes[0]))
+    }
+
+    pub fn class(&self) -> VehicleClass {
+        self.class
+    }
+
+    pub fn length(&self) -> f32 {
+        self.length
+    }
+
+    pub fn width(&self) -> f32 {
+        self.width
+    }
+
+    pub fn driver_behaviour(&self) -> DriverBehaviour {
+        self.driver_behaviour.unwrap_or_default()
+    }
+
+    pub fn has_driver_behaviour(&self) -> bool {
+        self.driver_behaviour.is_some()
+    }
+
+    pub fn transit_trip(&self) -> Option<&TransitTrip> {
+        self.transit_trip.as_ref()
+    }
+
+    pub fn battery(&self) -> Option<&Battery> {
+        self.battery.as_ref()
+    }
+
+    pub(crate) fn battery_mut(&mut self) -> Option<&mut Battery> {
+        self.battery.as_mut()
+    }
+
+    /// Stops the road user on the spot. It won't move anymore.
+    pub fn halt(&mut self) {
+        self.halted = true;
+        self.current_speed = 0.0;
+    }
+
+    pub fn is_halted(&self) -> bool {
+        self.halted
+    }
 }
@@ -0,0 +1,124 @@
+use std::f32::consts::PI;
+
+use serde::{Deserialize, Serialize};
+
+pub(crate) const GRAVITY: f32 = 9.81; // m/s/s
+const AIR_DENSITY: f32 = 1.2; // kg/m3
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
+pub enum VehicleClass {
+    #[default]
+    PassengerCar,
+    Bus,
+    Truck,
+    ElectricCar,
+}
+
+impl VehicleClass {
+    /// Bumper to bumper length in meters
+    pub fn default_length(&self) -> f32 {
+        match self {
+            VehicleClass::PassengerCar => 4.5,
+            VehicleClass::ElectricCar => 4.7,
+            VehicleClass::Bus => 12.0,
+            VehicleClass::Truck => 16.5,
+        }
+    }
+
+    /// Width in meters
+    pub fn default_width(&self) -> f32 {
+        match self {
+            VehicleClass::PassengerCar => 1.8,
+            VehicleClass::ElectricCar => 1.85,
+            VehicleClass::Bus => 2.55,
+            VehicleClass::Truck => 2.55,
+        }
+    }
+
+    /// m/s/s
+    pub fn default_acceleration(&self) -> f32 {
+        match self {
+            VehicleClass::PassengerCar => 3.5,
+            VehicleClass::ElectricCar => 4.0,
+            VehicleClass::Bus => 1.2,
+            VehicleClass::Truck => 1.0,
+        }
+    }
+
+    /// m/s/s
+    pub fn default_deceleration(&self) -> f32 {
+        match self {
+            VehicleClass::PassengerCar | VehicleClass::ElectricCar => 5.0,
+            VehicleClass::Bus => 3.5,
+            VehicleClass::Truck => 3.0,
+        }
+    }
+
+    /// rads/s
+    pub fn default_max_steering_angle(&self) -> f32 {
+        match self {
+            VehicleClass::PassengerCar | VehicleClass::ElectricCar => PI / 2.0,
+            VehicleClass::Bus => PI / 4.0,
+            VehicleClass::Truck => PI / 5.0,
+        }
+    }
+
+    /// Loaded mass in kg
+    pub fn mass(&self) -> f32 {
+        match self {
+            VehicleClass::PassengerCar => 1400.0,
+            VehicleClass::ElectricCar => 1900.0,
+            VehicleClass::Bus => 15000.0,
+            VehicleClass::Truck => 30000.0,
+        }
+    }
+
+    /// The engine power in W. Together with the mass this limits the acceleration at speed
+    /// and on climbs.
+    pub fn power(&self) -> f32 {
+        match self {
+            VehicleClass::PassengerCar => 90_000.0,
+            VehicleClass::Bus => 220_000.0,
+            VehicleClass::Truck => 330_000.0,
+            VehicleClass::ElectricCar => 150_000.0,
+        }
+    }
+
+    /// Drag coefficient times the frontal area in m2
+    pub fn drag_area(&self) -> f32 {
+        match self {
+            VehicleClass::PassengerCar => 0.7,
+            VehicleClass::Bus => 6.0,
+            VehicleClass::Truck => 5.5,
+            VehicleClass::ElectricCar => 0.55,
+        }
+    }
+
+    /// The rolling resistance coefficient of the tyres
+    pub fn rolling_resistance(&self) -> f32 {
+        match self {
+            VehicleClass::PassengerCar => 0.012,
+            VehicleClass::Bus => 0.008,
+            VehicleClass::Truck => 0.007,
+            VehicleClass::ElectricCar => 0.009,
+        }
+    }
+
+    /// The force in N the wheels deliver to drive at the speed with the acceleration, against
+    /// the rolling resistance, the air drag and the slope. The grade is the sine of the slope,
+    /// negative when going downhill. The force is negative when the vehicle has to brake.
+    pub fn road_load_force(&self, speed: f32, acceleration: f32, grade: f32) -> f32 {
+        let mass = self.mass();
+        mass * (acceleration + GRAVITY * grade)
+            + self.rolling_resistance() * mass * GRAVITY
+            + 0.5 * AIR_DENSITY * self.drag_area() * speed.powi(2)
+    }
+
+    /// The usable battery capacity in kWh of the classes that drive electrically
+    pub fn default_battery_capacity(&self) -> Option<f32> {
+        match self {
+            VehicleClass::ElectricCar => Some(60.0),
+            _ => None,
+        }
+    }
+}
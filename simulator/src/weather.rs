@@ -0,0 +1,123 @@
+use nalgebra::Point3;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum WeatherCondition {
+    #[default]
+    Dry,
+    Wet,
+    Snow,
+    Ice,
+    Fog,
+}
+
+/// How a condition changes the way vehicles are driven. All values are factors on the
+/// behaviour in dry conditions.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ConditionEffects {
+    /// The grip that's left to brake with
+    pub deceleration_factor: f32,
+    pub desired_speed_factor: f32,
+    pub headway_factor: f32,
+    pub corner_speed_factor: f32,
+}
+
+impl WeatherCondition {
+    pub fn effects(&self) -> ConditionEffects {
+        let (deceleration_factor, desired_speed_factor, headway_factor, corner_speed_factor) =
+            match self {
+                WeatherCondition::Dry => (1.0, 1.0, 1.0, 1.0),
+                WeatherCondition::Wet => (0.7, 0.9, 1.2, 0.85),
+                WeatherCondition::Snow => (0.4, 0.7, 1.5, 0.6),
+                WeatherCondition::Ice => (0.2, 0.5, 2.0, 0.4),
+                WeatherCondition::Fog => (1.0, 0.7, 1.4, 0.9),
+            };
+
+        ConditionEffects {
+            deceleration_factor,
+            desired_speed_factor,
+            headway_factor,
+            corner_speed_factor,
+        }
+    }
+}
+
+/// Conditions that change over time. Every entry is `(start time, condition)` and holds until
+/// the start of the next one.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct WeatherSchedule(Vec<(f32, WeatherCondition)>);
+
+impl WeatherSchedule {
+    pub fn new(mut schedule: Vec<(f32, WeatherCondition)>) -> Self {
+        schedule.sort_by(|a, b| a.0.total_cmp(&b.0));
+        Self(schedule)
+    }
+
+    pub fn constant(condition: WeatherCondition) -> Self {
+        Self(vec![(f32::NEG_INFINITY, condition)])
+    }
+
+    /// None before the first entry starts
+    pub fn condition_at(&self, time: f32) -> Option<WeatherCondition> {
+        self.0
+            .iter()
+            .rev()
+            .find(|(start, _)| *start <= time)
+            .map(|(_, condition)| *condition)
+    }
+}
+
+/// A circular area with its own weather, e.g. a fog bank or a bridge that freezes over
+#[derive(Debug, Clone, PartialEq)]
+pub struct WeatherZone {
+    center: Point3<f32>,
+    radius: f32, // m
+    schedule: WeatherSchedule,
+}
+
+impl WeatherZone {
+    pub fn new(center: Point3<f32>, radius: f32, schedule: WeatherSchedule) -> Self {
+        Self {
+            center,
+            radius,
+            schedule,
+        }
+    }
+
+    pub fn contains(&self, location: &Point3<f32>) -> bool {
+        (location.xy() - self.center.xy()).magnitude() <= self.radius
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Weather {
+    global: WeatherSchedule,
+    zones: Vec<WeatherZone>,
+}
+
+impl Weather {
+    pub fn new(global: WeatherSchedule) -> Self {
+        Self {
+            global,
+            zones: Vec::new(),
+        }
+    }
+
+    /// Zones added later take precedence where they overlap
+    pub fn add_zone(&mut self, zone: WeatherZone) {
+        self.zones.push(zone);
+    }
+
+    pub fn zones(&self) -> &[WeatherZone] {
+        self.zones.as_ref()
+    }
+
+    pub fn condition_at(&self, location: &Point3<f32>, time: f32) -> WeatherCondition {
+        self.zones
+            .iter()
+            .rev()
+            .filter(|zone| zone.contains(location))
+            .find_map(|zone| zone.schedule.condition_at(time))
+            .or_else(|| self.global.condition_at(time))
+            .unwrap_or_default()
+    }
+}
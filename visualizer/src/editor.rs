@@ -0,0 +1,625 @@
+use std::{mem, path::Path};
+
+use bevy::{prelude::*, window::PrimaryWindow};
+use bevy_inspector_egui::{bevy_egui::EguiContexts, egui};
+use nalgebra::Point3;
+use traffic_simulator::{
+    scenario::{NodeDefinition, Scenario, TrafficLightDefinition},
+    traffic_light::TrafficLightState,
+};
+
+use crate::{scenario_file::ScenarioFile, to_bevy, LoadedScenario, ResSim, SimulationLoaded};
+
+/// How close to a node a click has to be to pick it
+const PICK_RADIUS: f32 = 0.5; // m
+const DEFAULT_MAX_SPEED: f32 = 50.0 / 3.6; // m/s
+const DEFAULT_SAVE_PATH: &str = "scenario.json";
+
+/// Edits the scenario of the simulation with the mouse and a side panel.
+/// E switches between editing and simulating, the simulation stands still while editing.
+pub struct EditorPlugin;
+
+impl Plugin for EditorPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Editor>()
+            .add_startup_system(spawn_selection_marker)
+            .add_system(toggle_editor)
+            .add_systems(
+                (
+                    notice_reload,
+                    editor_panel,
+                    editor_shortcuts,
+                    edit_with_mouse,
+                    apply_edits,
+                )
+                    .chain()
+                    .distributive_run_if(editing),
+            )
+            .add_system(update_selection_marker);
+    }
+}
+
+pub fn editing(editor: Res<Editor>) -> bool {
+    editor.session.is_some()
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+enum Tool {
+    /// Select nodes and drag them around
+    #[default]
+    Move,
+    AddNode,
+    /// Click a node and then the node it should lead to
+    Connect,
+}
+
+#[derive(Resource, Default)]
+pub struct Editor {
+    session: Option<EditSession>,
+    tool: Tool,
+    save_path: String,
+    status: Option<String>,
+}
+
+struct EditSession {
+    /// The loaded scenario the edits are based on
+    base: Scenario,
+    scenario: Scenario,
+    /// The scenario after the last undo step. Changes become a step once no mouse button is
+    /// held, so dragging something is undone in one go.
+    committed: Scenario,
+    undo: Vec<Scenario>,
+    redo: Vec<Scenario>,
+    /// What the simulation was last built from. Only committed changes are applied, so
+    /// the network isn't rebuilt on every frame while dragging.
+    applied: Scenario,
+    selected: Option<u32>,
+    dragging: Option<u32>,
+}
+
+impl EditSession {
+    fn new(scenario: Scenario) -> Self {
+        Self {
+            base: scenario.clone(),
+            committed: scenario.clone(),
+            applied: scenario.clone(),
+            scenario,
+            undo: Vec::new(),
+            redo: Vec::new(),
+            selected: None,
+            dragging: None,
+        }
+    }
+
+    fn commit(&mut self) {
+        if self.scenario != self.committed {
+            self.undo
+                .push(mem::replace(&mut self.committed, self.scenario.clone()));
+            self.redo.clear();
+        }
+    }
+
+    fn can_undo(&self) -> bool {
+        !self.undo.is_empty() || self.scenario != self.committed
+    }
+
+    fn can_redo(&self) -> bool {
+        !self.redo.is_empty()
+    }
+
+    fn undo(&mut self) {
+        self.commit();
+        if let Some(previous) = self.undo.pop() {
+            self.redo.push(mem::replace(&mut self.scenario, previous));
+            self.after_history_change();
+        }
+    }
+
+    fn redo(&mut self) {
+        if let Some(next) = self.redo.pop() {
+            self.undo.push(mem::replace(&mut self.scenario, next));
+            self.after_history_change();
+        }
+    }
+
+    fn after_history_change(&mut self) {
+        self.committed = self.scenario.clone();
+        self.dragging = None;
+        if self.selected.is_some_and(|id| self.node(id).is_none()) {
+            self.selected = None;
+        }
+    }
+
+    fn node(&self, id: u32) -> Option<&NodeDefinition> {
+        self.scenario.nodes.iter().find(|node| node.id == id)
+    }
+
+    fn node_mut(&mut self, id: u32) -> Option<&mut NodeDefinition> {
+        self.scenario.nodes.iter_mut().find(|node| node.id == id)
+    }
+
+    /// The closest node within the pick radius of the point on the ground
+    fn node_at(&self, point: Vec2) -> Option<u32> {
+        self.scenario
+            .nodes
+            .iter()
+            .map(|node| {
+                let location = Vec2::new(node.location[0], node.location[1]);
+                (node.id, location.distance(point))
+            })
+            .filter(|(_, distance)| *distance <= PICK_RADIUS)
+            .min_by(|(_, a), (_, b)| a.total_cmp(b))
+            .map(|(id, _)| id)
+    }
+
+    fn add_node(&mut self, point: Vec2) -> u32 {
+        let id = self
+            .scenario
+            .nodes
+            .iter()
+            .map(|node| node.id + 1)
+            .max()
+            .unwrap_or_default();
+
+        self.scenario.nodes.push(NodeDefinition {
+            id,
+            location: [point.x, point.y, 0.0],
+            max_speed: DEFAULT_MAX_SPEED,
+            next_nodes: Vec::new(),
+            adjacent_node_right: None,
+            adjacent_node_left: None,
+        });
+        id
+    }
+
+    /// Adds an edge from one node to the other, or removes it if it's already there
+    fn toggle_connection(&mut self, from: u32, to: u32) {
+        if let Some(node) = self.node_mut(from) {
+            if node.next_nodes.contains(&to) {
+                node.next_nodes.retain(|next| *next != to);
+            } else {
+                node.next_nodes.push(to);
+            }
+        }
+    }
+
+    /// Removes the node and everything that refers to it
+    fn remove_node(&mut self, id: u32) {
+        let scenario = &mut self.scenario;
+        scenario.nodes.retain(|node| node.id != id);
+        for node in scenario.nodes.iter_mut() {
+            node.next_nodes.retain(|next| *next != id);
+            for adjacent in [&mut node.adjacent_node_right, &mut node.adjacent_node_left] {
+                if *adjacent == Some(id) {
+                    *adjacent = None;
+                }
+            }
+        }
+        scenario.traffic_lights.retain(|light| light.node != id);
+        scenario
+            .demand
+            .retain(|demand| demand.origin() != id && demand.destination() != id);
+        scenario
+            .timeline
+            .retain(|event| !event.action.nodes().contains(&id));
+
+        if self.selected == Some(id) {
+            self.selected = None;
+        }
+    }
+
+    fn set_traffic_light(&mut self, node: u32, enabled: bool) {
+        self.scenario
+            .traffic_lights
+            .retain(|light| light.node != node);
+        if enabled {
+            self.scenario.traffic_lights.push(TrafficLightDefinition {
+                node,
+                schema: vec![
+                    (20.0, TrafficLightState::Green),
+                    (3.0, TrafficLightState::Orange),
+                    (20.0, TrafficLightState::Red),
+                ],
+            });
+        }
+    }
+}
+
+/// Starts editing the loaded scenario, or simulates what has been edited
+fn toggle_editor(
+    keys: Res<Input<KeyCode>>,
+    mut contexts: EguiContexts,
+    mut editor: ResMut<Editor>,
+    mut scenario: ResMut<LoadedScenario>,
+    mut simulator: ResMut<ResSim>,
+    mut loaded: EventWriter<SimulationLoaded>,
+    scenario_file: Option<Res<ScenarioFile>>,
+) {
+    if contexts.ctx_mut().wants_keyboard_input() || !keys.just_pressed(KeyCode::E) {
+        return;
+    }
+
+    match editor.session.take() {
+        Some(session) => scenario.0 = session.scenario,
+        None => {
+            editor.session = Some(EditSession::new(scenario.0.clone()));
+            if editor.save_path.is_empty() {
+                editor.save_path = scenario_file.map_or(DEFAULT_SAVE_PATH.to_string(), |file| {
+                    file.path().display().to_string()
+                });
+            }
+        }
+    }
+
+    // Editing starts from, and simulating starts with, a fresh simulation of the scenario
+    simulator.0 = scenario.0.clone().into_simulator();
+    loaded.send(SimulationLoaded);
+}
+
+fn editor_panel(
+    mut contexts: EguiContexts,
+    mut editor: ResMut<Editor>,
+    mut loaded_scenario: ResMut<LoadedScenario>,
+    scenario_file: Option<Res<ScenarioFile>>,
+) {
+    let Editor {
+        session: Some(session),
+        tool,
+        save_path,
+        status,
+    } = &mut *editor
+    else {
+        return;
+    };
+
+    egui::SidePanel::left("editor").show(contexts.ctx_mut(), |ui| {
+        ui.heading("Editor");
+        ui.label("E simulates the scenario, Ctrl+Z undoes, Ctrl+Y redoes");
+
+        ui.horizontal(|ui| {
+            ui.selectable_value(tool, Tool::Move, "Move");
+            ui.selectable_value(tool, Tool::AddNode, "Add nodes");
+            ui.selectable_value(tool, Tool::Connect, "Connect");
+        });
+        ui.label(match tool {
+            Tool::Move => "Click a node to select it, drag to move it",
+            Tool::AddNode => "Click the ground to place a node",
+            Tool::Connect => "Click a node, then the node it leads to",
+        });
+
+        ui.horizontal(|ui| {
+            if ui
+                .add_enabled(session.can_undo(), egui::Button::new("Undo"))
+                .clicked()
+            {
+                session.undo();
+            }
+            if ui
+                .add_enabled(session.can_redo(), egui::Button::new("Redo"))
+                .clicked()
+            {
+                session.redo();
+            }
+        });
+
+        ui.separator();
+        match session.selected {
+            Some(id) => node_properties(ui, session, id),
+            None => {
+                ui.label("No node selected");
+            }
+        }
+
+        ui.separator();
+        ui.horizontal(|ui| {
+            ui.text_edit_singleline(save_path);
+            if ui.button("Save").clicked() {
+                session.commit();
+                *status = Some(match session.scenario.save(&*save_path) {
+                    Ok(()) => {
+                        // Saving to the watched file reloads it, which isn't a change by
+                        // someone else
+                        if scenario_file
+                            .as_ref()
+                            .is_some_and(|file| file.path() == Path::new(save_path))
+                        {
+                            session.base = session.scenario.clone();
+                            loaded_scenario.0 = session.scenario.clone();
+                        }
+                        format!("Saved to {save_path}")
+                    }
+                    Err(error) => format!("Could not save: {error}"),
+                });
+            }
+        });
+        if let Some(status) = status {
+            ui.label(status.as_str());
+        }
+    });
+}
+
+fn node_properties(ui: &mut egui::Ui, session: &mut EditSession, id: u32) {
+    let other_nodes = session
+        .scenario
+        .nodes
+        .iter()
+        .map(|node| node.id)
+        .filter(|node| *node != id)
+        .collect::<Vec<_>>();
+    let mut has_traffic_light = session
+        .scenario
+        .traffic_lights
+        .iter()
+        .any(|light| light.node == id);
+    let Some(node) = session.node_mut(id) else {
+        return;
+    };
+
+    ui.label(format!(
+        "Node {id} at ({:.1}, {:.1})",
+        node.location[0], node.location[1]
+    ));
+
+    let mut max_speed = node.max_speed * 3.6;
+    ui.horizontal(|ui| {
+        ui.label("Max speed");
+        if ui
+            .add(
+                egui::DragValue::new(&mut max_speed)
+                    .clamp_range(0.0..=200.0)
+                    .suffix(" km/h"),
+            )
+            .changed()
+        {
+            node.max_speed = max_speed / 3.6;
+        }
+    });
+
+    let mut removed = None;
+    for next in node.next_nodes.iter() {
+        ui.horizontal(|ui| {
+            ui.label(format!("Leads to {next}"));
+            if ui.small_button("Remove").clicked() {
+                removed = Some(*next);
+            }
+        });
+    }
+    if let Some(removed) = removed {
+        node.next_nodes.retain(|next| *next != removed);
+    }
+
+    node_choice(
+        ui,
+        "Adjacent right",
+        &mut node.adjacent_node_right,
+        &other_nodes,
+    );
+    node_choice(
+        ui,
+        "Adjacent left",
+        &mut node.adjacent_node_left,
+        &other_nodes,
+    );
+
+    if ui
+        .checkbox(&mut has_traffic_light, "Traffic light")
+        .changed()
+    {
+        session.set_traffic_light(id, has_traffic_light);
+    }
+
+    if ui.button("Delete node").clicked() {
+        session.remove_node(id);
+    }
+}
+
+fn node_choice(ui: &mut egui::Ui, label: &str, value: &mut Option<u32>, nodes: &[u32]) {
+    egui::ComboBox::from_label(label)
+        .selected_text(value.map_or("None".to_string(), |id| id.to_string()))
+        .show_ui(ui, |ui| {
+            ui.selectable_value(value, None, "None");
+            for id in nodes {
+                ui.selectable_value(value, Some(*id), id.to_string());
+            }
+        });
+}
+
+fn editor_shortcuts(
+    keys: Res<Input<KeyCode>>,
+    mut contexts: EguiContexts,
+    mut editor: ResMut<Editor>,
+) {
+    let Some(session) = editor.session.as_mut() else {
+        return;
+    };
+    if contexts.ctx_mut().wants_keyboard_input() {
+        return;
+    }
+
+    let control = keys.any_pressed([KeyCode::LControl, KeyCode::RControl]);
+    let shift = keys.any_pressed([KeyCode::LShift, KeyCode::RShift]);
+    if control && keys.just_pressed(KeyCode::Z) {
+        if shift {
+            session.redo();
+        } else {
+            session.undo();
+        }
+    }
+    if control && keys.just_pressed(KeyCode::Y) {
+        session.redo();
+    }
+    if keys.just_pressed(KeyCode::Delete) {
+        if let Some(id) = session.selected {
+            session.remove_node(id);
+        }
+    }
+}
+
+fn edit_with_mouse(
+    buttons: Res<Input<MouseButton>>,
+    mut contexts: EguiContexts,
+    mut editor: ResMut<Editor>,
+    windows: Query<&Window, With<PrimaryWindow>>,
+    cameras: Query<(&Camera, &GlobalTransform)>,
+) {
+    let Editor {
+        session: Some(session),
+        tool,
+        ..
+    } = &mut *editor
+    else {
+        return;
+    };
+
+    if !buttons.pressed(MouseButton::Left) {
+        session.dragging = None;
+        session.commit();
+    }
+
+    let Some(point) = cursor_on_ground(&windows, &cameras) else {
+        return;
+    };
+
+    if buttons.just_pressed(MouseButton::Left) && !contexts.ctx_mut().wants_pointer_input() {
+        let picked = session.node_at(point);
+        match tool {
+            Tool::Move => {
+                session.selected = picked;
+                session.dragging = picked;
+            }
+            Tool::AddNode => {
+                session.selected = Some(picked.unwrap_or_else(|| session.add_node(point)));
+            }
+            Tool::Connect => match (session.selected, picked) {
+                (Some(from), Some(to)) if from != to => {
+                    session.toggle_connection(from, to);
+                    session.selected = Some(to);
+                }
+                (_, picked) => session.selected = picked,
+            },
+        }
+    }
+
+    if let Some(id) = session.dragging {
+        if let Some(node) = session.node_mut(id) {
+            node.location[0] = point.x;
+            node.location[1] = point.y;
+        }
+    }
+}
+
+/// Where the cursor points at the ground, in the coordinates of the simulation
+fn cursor_on_ground(
+    windows: &Query<&Window, With<PrimaryWindow>>,
+    cameras: &Query<(&Camera, &GlobalTransform)>,
+) -> Option<Vec2> {
+    let cursor = windows.get_single().ok()?.cursor_position()?;
+    let (camera, transform) = cameras.iter().find(|(camera, _)| camera.is_active)?;
+    let ray = camera.viewport_to_world(transform, cursor)?;
+    let ground = ray.get_point(ray.intersect_plane(Vec3::ZERO, Vec3::Y)?);
+
+    Some(Vec2::new(ground.x, ground.z))
+}
+
+/// The scenario file can be reloaded while editing, which replaces the simulation. The
+/// edits are kept and simulated when the editor closes, so they'd overwrite the new version.
+fn notice_reload(
+    mut editor: ResMut<Editor>,
+    scenario: Res<LoadedScenario>,
+    mut simulator: ResMut<ResSim>,
+    mut loaded: EventWriter<SimulationLoaded>,
+) {
+    let Editor {
+        session: Some(session),
+        status,
+        ..
+    } = &mut *editor
+    else {
+        return;
+    };
+    if !scenario.is_changed() || scenario.0 == session.base {
+        return;
+    }
+
+    session.base = scenario.0.clone();
+    *status = Some(
+        "The scenario file changed while editing. Simulating or saving the edits replaces it."
+            .to_string(),
+    );
+
+    // Show the edits again instead of the reloaded scenario
+    simulator.0 = session.applied.clone().into_simulator();
+    loaded.send(SimulationLoaded);
+}
+
+/// Rebuilds the simulation when a change is committed, so the network is drawn as edited
+fn apply_edits(
+    mut editor: ResMut<Editor>,
+    mut simulator: ResMut<ResSim>,
+    mut loaded: EventWriter<SimulationLoaded>,
+) {
+    let Editor {
+        session: Some(session),
+        status,
+        ..
+    } = &mut *editor
+    else {
+        return;
+    };
+    if session.committed == session.applied {
+        return;
+    }
+
+    match session.committed.validate() {
+        Ok(()) => {
+            simulator.0 = session.committed.clone().into_simulator();
+            session.applied = session.committed.clone();
+            loaded.send(SimulationLoaded);
+        }
+        Err(error) => *status = Some(error.to_string()),
+    }
+}
+
+#[derive(Component)]
+struct SelectionMarker;
+
+fn spawn_selection_marker(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    commands.spawn((
+        PbrBundle {
+            mesh: meshes.add(Mesh::from(shape::Cube { size: 0.35 })),
+            material: materials.add(StandardMaterial {
+                base_color: Color::rgba(1.0, 0.5, 0.0, 0.6),
+                alpha_mode: AlphaMode::Blend,
+                ..default()
+            }),
+            visibility: Visibility::Hidden,
+            ..default()
+        },
+        SelectionMarker,
+        Name::new("Selection marker"),
+    ));
+}
+
+fn update_selection_marker(
+    editor: Res<Editor>,
+    mut markers: Query<(&mut Transform, &mut Visibility), With<SelectionMarker>>,
+) {
+    let selected = editor.session.as_ref().and_then(|session| {
+        let node = session.node(session.selected?)?;
+        Some(to_bevy(Point3::from(node.location)))
+    });
+
+    for (mut transform, mut visibility) in markers.iter_mut() {
+        match selected {
+            Some(location) => {
+                transform.translation = location;
+                *visibility = Visibility::Visible;
+            }
+            None => *visibility = Visibility::Hidden,
+        }
+    }
+}
@@ -1,139 +1,271 @@
-use std::f32::consts::PI;
+use std::{collections::HashMap, f32::consts::PI, path::PathBuf};
 
 use bevy::input::common_conditions::input_toggle_active;
-use traffic_simulator::{Simulator, user::RoadUser, road::{RoadNetwork, self}};
-use nalgebra::{Point3, Vector3};
 use bevy::{pbr::CascadeShadowConfigBuilder, prelude::*};
-use bevy_inspector_egui::quick::WorldInspectorPlugin;
+use bevy_inspector_egui::{bevy_egui::EguiPlugin, quick::WorldInspectorPlugin};
+use nalgebra::{Point3, Vector3};
+use traffic_simulator::{
+    demand::TrafficDemand,
+    road::{self, RoadNetwork},
+    scenario::{NodeDefinition, Scenario, TrafficLightDefinition},
+    traffic_light::TrafficLightState,
+    user::RoadUser,
+    Simulator,
+};
+
+mod editor;
+mod playback;
+mod scenario_file;
+
+use editor::EditorPlugin;
+use playback::PlaybackPlugin;
+use scenario_file::ScenarioFilePlugin;
+
+const ROAD_WIDTH: f32 = 0.6;
+const ROAD_THICKNESS: f32 = 0.05;
 
 fn main() {
     const TIME_STEP: f32 = 1.0 / 60.0;
+
+    // The scenario file to run, the demo network runs without one
+    let scenario_path = std::env::args_os().nth(1).map(PathBuf::from);
+
     App::new()
         .add_plugins(DefaultPlugins)
-        .init_resource::<ResSim>()
+        .add_plugin(EguiPlugin)
+        .add_event::<SimulationLoaded>()
+        .insert_resource(ResSim(demo_scenario().into_simulator()))
+        .insert_resource(LoadedScenario(demo_scenario()))
+        .init_resource::<RoadUserEntities>()
+        .init_resource::<SignalMaterials>()
+        .register_type::<Ru>()
+        .register_type::<SignalHead>()
         .add_startup_system(setup)
-        .add_system(tick_simulation)
+        .add_plugin(ScenarioFilePlugin {
+            path: scenario_path,
+        })
+        .add_plugin(EditorPlugin)
+        .add_plugin(PlaybackPlugin)
+        .add_system(spawn_network)
+        .add_system(sync_road_users.after(spawn_network))
+        .add_system(update_signal_heads)
         .add_plugin(
-            WorldInspectorPlugin::default().run_if(input_toggle_active(true, KeyCode::Escape))
+            WorldInspectorPlugin::default().run_if(input_toggle_active(true, KeyCode::Escape)),
         )
         .insert_resource(FixedTime::new_from_secs(TIME_STEP))
         .run();
 }
 
+/// The `RoadUser::id` of the entity, shown in the inspector
+#[derive(Component, Reflect)]
+struct Ru(u32);
 
+#[derive(Resource)]
+struct ResSim(Simulator);
 
+/// Sent when `ResSim` has a new simulation, so the scene has to be built again
+pub struct SimulationLoaded;
+
+/// Part of the drawn road network, despawned when another simulation is loaded
 #[derive(Component)]
-struct Ru(u32);
+struct NetworkEntity;
 
+/// The entity of every road user in the simulation, by `RoadUser::id`
+#[derive(Resource, Default)]
+struct RoadUserEntities(HashMap<u32, Entity>);
+
+/// Shared by all road user entities, they're scaled to the size of the vehicle
 #[derive(Resource)]
-struct ResSim(Simulator);
+struct RoadUserAssets {
+    mesh: Handle<Mesh>,
+    material: Handle<StandardMaterial>,
+}
 
-impl FromWorld for ResSim {
+/// The signal head of the traffic light at the node
+#[derive(Component, Reflect)]
+struct SignalHead {
+    node: u32,
+}
+
+#[derive(Resource)]
+struct SignalMaterials {
+    red: Handle<StandardMaterial>,
+    orange: Handle<StandardMaterial>,
+    green: Handle<StandardMaterial>,
+}
+
+impl SignalMaterials {
+    fn for_state(&self, state: TrafficLightState) -> Handle<StandardMaterial> {
+        match state {
+            TrafficLightState::Red => self.red.clone(),
+            TrafficLightState::Orange => self.orange.clone(),
+            TrafficLightState::Green => self.green.clone(),
+        }
+    }
+}
+
+impl FromWorld for SignalMaterials {
     fn from_world(world: &mut World) -> Self {
-        ResSim(Simulator::new(RoadNetwork::new(
-            [
-                (
-                    0,
-                    road::Node::new(
-                        0,
-                        Point3::new(8.0, 0.0, 0.0),
-                        30.0 / 3.6, // 30kph
-                        vec![1],
-                        None,
-                        None,
-                    ),
-                ),
-                (
-                    1,
-                    road::Node::new(
-                        1,
-                        Point3::new(10.0, 0.0, 0.0),
-                        10.0 / 3.6, // 30kph
-                        vec![2, 4],
-                        None,
-                        None,
-                    ),
-                ),
-                (
-                    2,
-                    road::Node::new(
-                        2,
-                        Point3::new(10.5, 1.0, 0.0),
-                        5.0 / 3.6, // 30kph
-                        vec![3],
-                        None,
-                        None,
-                    ),
-                ),
-                (
-                    3,
-                    road::Node::new(
-                        3,
-                        Point3::new(11.0, 20.0, 0.0),
-                        30.0 / 3.6, // 30kph
-                        Vec::new(),
-                        None,
-                        None,
-                    ),
-                ),
-                (
-                    4,
-                    road::Node::new(
-                        4,
-                        Point3::new(10.5, -1.0, 0.0),
-                        5.0 / 3.6, // 30kph
-                        vec![5],
-                        None,
-                        None,
-                    ),
-                ),
-                (
-                    5,
-                    road::Node::new(
-                        5,
-                        Point3::new(11.0, -20.0, 0.0),
-                        30.0 / 3.6, // 30kph
-                        Vec::new(),
-                        None,
-                        None,
-                    ),
-                ),
-            ]
-                .into(),
-        )))
+        let mut materials = world.resource_mut::<Assets<StandardMaterial>>();
+        let mut lit = |color: Color| {
+            materials.add(StandardMaterial {
+                base_color: color,
+                emissive: color,
+                ..default()
+            })
+        };
+
+        SignalMaterials {
+            red: lit(Color::rgb(0.9, 0.1, 0.1)),
+            orange: lit(Color::rgb(1.0, 0.55, 0.0)),
+            green: lit(Color::rgb(0.1, 0.8, 0.2)),
+        }
     }
 }
 
-fn tick_simulation(
+/// The scenario the simulation was built from, where the editor starts
+#[derive(Resource)]
+pub struct LoadedScenario(Scenario);
+
+/// A junction with a traffic light, where traffic goes left or right
+fn demo_scenario() -> Scenario {
+    let node = |id, location, max_speed, next_nodes| NodeDefinition {
+        id,
+        location,
+        max_speed,
+        next_nodes,
+        adjacent_node_right: None,
+        adjacent_node_left: None,
+    };
+
+    Scenario {
+        nodes: vec![
+            node(0, [8.0, 0.0, 0.0], 30.0 / 3.6, vec![1]),
+            node(1, [10.0, 0.0, 0.0], 10.0 / 3.6, vec![2, 4]),
+            node(2, [10.5, 1.0, 0.0], 5.0 / 3.6, vec![3]),
+            node(3, [11.0, 20.0, 0.0], 30.0 / 3.6, Vec::new()),
+            node(4, [10.5, -1.0, 0.0], 5.0 / 3.6, vec![5]),
+            node(5, [11.0, -20.0, 0.0], 30.0 / 3.6, Vec::new()),
+        ],
+        traffic_lights: vec![TrafficLightDefinition {
+            node: 1,
+            schema: vec![
+                (10.0, TrafficLightState::Green),
+                (3.0, TrafficLightState::Orange),
+                (10.0, TrafficLightState::Red),
+            ],
+        }],
+        demand: vec![
+            TrafficDemand::new(0, 0, 3, 60.0),
+            TrafficDemand::new(1, 0, 5, 60.0),
+        ],
+        timeline: Vec::new(),
+        seed: None,
+        coordinate_reference: None,
+    }
+}
+
+/// Spawns entities for new road users, despawns the ones of road users that have left and
+/// moves the rest to where they are in the simulation
+fn sync_road_users(
     mut commands: Commands,
-    mut simulator: ResMut<ResSim>,
-    mut ru_query: Query<(&Transform, &Ru)>,
+    simulator: Res<ResSim>,
+    assets: Res<RoadUserAssets>,
+    mut entities: ResMut<RoadUserEntities>,
+    mut transforms: Query<&mut Transform, With<Ru>>,
 ) {
-    let (transform, id) = ru_query.single_mut();
-    let rn = simulator.0.current_road_users();
-    let ru = rn.iter().find(|ru| ru.id == id);
-    transform = Transform::from_xyz(ru.location().x, ru.location().z, ru.location().y);
-    simulator.0.tick();
+    let road_users = simulator.0.current_road_users();
+
+    entities.0.retain(|id, entity| {
+        let active = road_users.iter().any(|ru| ru.id == *id);
+        if !active {
+            commands.entity(*entity).despawn();
+        }
+        active
+    });
+
+    for ru in road_users {
+        let transform = road_user_transform(ru);
+
+        match entities.0.get(&ru.id) {
+            Some(entity) => {
+                if let Ok(mut current) = transforms.get_mut(*entity) {
+                    *current = transform;
+                }
+            }
+            None => {
+                let entity = commands
+                    .spawn((
+                        PbrBundle {
+                            mesh: assets.mesh.clone(),
+                            material: assets.material.clone(),
+                            transform,
+                            ..default()
+                        },
+                        Ru(ru.id),
+                        Name::new(format!("Road user {}", ru.id)),
+                    ))
+                    .id();
+                entities.0.insert(ru.id, entity);
+            }
+        }
+    }
+}
+
+fn update_signal_heads(
+    simulator: Res<ResSim>,
+    signal_materials: Res<SignalMaterials>,
+    mut heads: Query<(&SignalHead, &mut Handle<StandardMaterial>)>,
+) {
+    let traffic_lights = simulator.0.traffic_lights();
+    for (head, mut material) in heads.iter_mut() {
+        if let Some(light) = traffic_lights
+            .iter()
+            .find(|light| light.node() == head.node)
+        {
+            *material = signal_materials.for_state(light.get_state());
+        }
+    }
+}
+
+fn road_user_transform(ru: &RoadUser) -> Transform {
+    const HEIGHT: f32 = 1.5;
+
+    let location = to_bevy(ru.location());
+    let transform = Transform::from_translation(location + Vec3::Y * HEIGHT / 2.0)
+        .with_scale(Vec3::new(ru.width(), HEIGHT, ru.length()));
+
+    // The box is as long as the vehicle along its z axis, which `looking_to` points forward
+    let direction = ru.current_direction();
+    if direction.magnitude() > f32::EPSILON {
+        transform.looking_to(to_bevy_vector(direction), Vec3::Y)
+    } else {
+        transform
+    }
+}
+
+/// The simulation has z up, Bevy has y up
+fn to_bevy(location: Point3<f32>) -> Vec3 {
+    Vec3::new(location.x, location.z, location.y)
+}
+
+fn to_bevy_vector(vector: Vector3<f32>) -> Vec3 {
+    Vec3::new(vector.x, vector.z, vector.y)
 }
 
 fn setup(
     mut commands: Commands,
     mut meshes: ResMut<Assets<Mesh>>,
     mut materials: ResMut<Assets<StandardMaterial>>,
-    mut sim: ResMut<ResSim>,
+    mut loaded: EventWriter<SimulationLoaded>,
 ) {
-    let mut simulator = &mut sim.0;
-    simulator.add_manual_road_users(RoadUser::new(
-        Point3::new(0.0, 0.0, 0.0),
-        0.0,
-        3.5,
-        5.0,
-        PI / 2.0,
-        0,
-        5,
-        &simulator.road_network(),
-    ));
-    
+    loaded.send(SimulationLoaded);
+
+    commands.insert_resource(RoadUserAssets {
+        mesh: meshes.add(Mesh::from(shape::Cube { size: 1.0 })),
+        material: materials.add(Color::rgb(1.0, 1.0, 0.0).into()),
+    });
+
     // camera
     commands.spawn(Camera3dBundle {
         transform: Transform::from_xyz(20.0, 15.0, 15.0).looking_at(Vec3::ZERO, Vec3::Y),
@@ -155,32 +287,8 @@ fn setup(
         transform: Transform::from_xyz(0.0, 0.0, 0.0),
         ..default()
     });
-     */      
-    let rn = simulator.road_network();
-
-    rn.all_node_ids().for_each(|node_id| {
-        let node = rn.find_node(node_id);
-        draw_node_marker(&mut commands, &mut meshes, &mut materials, &node);
-        node.next_nodes(rn).for_each(|next_node|{
-            let from = node;
-            let to = next_node;
-            draw_road(&mut commands, &mut meshes, &mut materials, &from, &to);
-            draw_node_marker(&mut commands, &mut meshes, &mut materials, &next_node);
-        });
-    });
+     */
 
-    let road_users = simulator.current_road_users();
-    road_users.iter().for_each(|ru| {
-        
-        commands.spawn((PbrBundle {
-            mesh: meshes.add(Mesh::from(shape::Cube { size: 0.5 })),
-            material: materials.add(Color::rgb(1.0, 1.0, 0.0).into()),
-            transform: Transform::from_xyz(ru.location().x, ru.location().z, ru.location().y),
-            ..default()
-        }, Ru(ru.id)));
-        
-    });
-    
     // light
     commands.spawn(DirectionalLightBundle {
         directional_light: DirectionalLight {
@@ -206,6 +314,58 @@ fn setup(
     });
 }
 
+/// Draws the road network of the simulation, replacing the one of the simulation before it
+#[allow(clippy::too_many_arguments)]
+fn spawn_network(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    signal_materials: Res<SignalMaterials>,
+    simulator: Res<ResSim>,
+    mut loaded: EventReader<SimulationLoaded>,
+    mut road_user_entities: ResMut<RoadUserEntities>,
+    network_entities: Query<Entity, With<NetworkEntity>>,
+) {
+    if loaded.is_empty() {
+        return;
+    }
+    loaded.clear();
+
+    for entity in network_entities
+        .iter()
+        .chain(road_user_entities.0.drain().map(|(_, entity)| entity))
+    {
+        commands.entity(entity).despawn();
+    }
+
+    let simulator = &simulator.0;
+    let rn = simulator.road_network();
+    rn.all_node_ids().for_each(|node_id| {
+        let node = rn.find_node(node_id);
+        draw_node_marker(&mut commands, &mut meshes, &mut materials, node);
+        node.next_nodes(rn).for_each(|next_node| {
+            let from = node;
+            let to = next_node;
+            draw_road(&mut commands, &mut meshes, &mut materials, from, to);
+            draw_node_marker(&mut commands, &mut meshes, &mut materials, next_node);
+        });
+    });
+
+    for light in simulator.traffic_lights() {
+        if rn.contains_node(light.node()) {
+            draw_traffic_light(
+                &mut commands,
+                &mut meshes,
+                &mut materials,
+                &signal_materials,
+                rn,
+                light.node(),
+                light.get_state(),
+            );
+        }
+    }
+}
+
 fn draw_road(
     commands: &mut Commands,
     meshes: &mut ResMut<Assets<Mesh>>,
@@ -214,35 +374,107 @@ fn draw_road(
     to: &road::Node,
 ) {
     let vec_to: Vector3<f32> = from.vector_to(to);
-    let road_width = 0.6;
-    let road_thickness = 0.05;
     let road_length = vec_to.magnitude();
-    let road_box = shape::Box::new(road_width, road_thickness, road_length);
+    let road_box = shape::Box::new(ROAD_WIDTH, ROAD_THICKNESS, road_length);
     let move_vec = vec_to * 0.5;
     let translation = from.location() + move_vec;
 
-    let transform = Transform::from_xyz(translation.x, translation.z, translation.y)
-        .looking_at((to.location().x, to.location().z, to.location().y).into(), Vec3::Y);
+    let transform = Transform::from_translation(to_bevy(translation))
+        .looking_at(to_bevy(to.location()), Vec3::Y);
 
     // Road
-    commands.spawn(PbrBundle {
-        mesh: meshes.add(Mesh::from(road_box)),
-        material: materials.add(Color::rgb(0.2, 0.2, 0.2).into()),
-        transform,
-        ..default()
-    });    
+    commands.spawn((
+        PbrBundle {
+            mesh: meshes.add(Mesh::from(road_box)),
+            material: materials.add(Color::rgb(0.2, 0.2, 0.2).into()),
+            transform,
+            ..default()
+        },
+        NetworkEntity,
+    ));
 }
 
 fn draw_node_marker(
     commands: &mut Commands,
     meshes: &mut ResMut<Assets<Mesh>>,
     materials: &mut ResMut<Assets<StandardMaterial>>,
-    node: &road::Node
+    node: &road::Node,
 ) {
-    commands.spawn(PbrBundle {
-        mesh: meshes.add(Mesh::from(shape::Cube { size: 0.2 })),
-        material: materials.add(Color::rgb(0.8, 0.7, 0.6).into()),
-        transform: Transform::from_xyz(node.location().x, node.location().z, node.location().y),
-        ..default()
-    });
+    commands.spawn((
+        PbrBundle {
+            mesh: meshes.add(Mesh::from(shape::Cube { size: 0.2 })),
+            material: materials.add(Color::rgb(0.8, 0.7, 0.6).into()),
+            transform: Transform::from_translation(to_bevy(node.location())),
+            ..default()
+        },
+        NetworkEntity,
+    ));
+}
+
+/// A stop line across the road at the node, where vehicles wait for the light, and a pole
+/// with the signal head on the right side of the road
+fn draw_traffic_light(
+    commands: &mut Commands,
+    meshes: &mut ResMut<Assets<Mesh>>,
+    materials: &mut ResMut<Assets<StandardMaterial>>,
+    signal_materials: &SignalMaterials,
+    network: &RoadNetwork,
+    node_id: u32,
+    state: TrafficLightState,
+) {
+    const POLE_HEIGHT: f32 = 1.0;
+
+    let node = network.find_node(node_id);
+
+    // The direction traffic arrives from, or leaves in if nothing leads to the node
+    let direction = network
+        .all_node_ids()
+        .map(|id| network.find_node(id))
+        .find(|from| from.next_node_ids().contains(&node_id))
+        .map(|from| from.direction_to(node))
+        .or_else(|| {
+            node.next_nodes(network)
+                .next()
+                .map(|to| node.direction_to(to))
+        })
+        .unwrap_or_else(Vector3::x);
+    let right = Vector3::new(direction.y, -direction.x, 0.0);
+
+    let location = to_bevy(node.location());
+    commands.spawn((
+        PbrBundle {
+            mesh: meshes.add(Mesh::from(shape::Box::new(
+                ROAD_WIDTH,
+                ROAD_THICKNESS / 5.0,
+                0.05,
+            ))),
+            material: materials.add(Color::WHITE.into()),
+            transform: Transform::from_translation(location + Vec3::Y * ROAD_THICKNESS / 2.0)
+                .looking_to(to_bevy_vector(direction), Vec3::Y),
+            ..default()
+        },
+        NetworkEntity,
+    ));
+
+    let pole = location + to_bevy_vector(right * (ROAD_WIDTH / 2.0 + 0.1));
+    commands.spawn((
+        PbrBundle {
+            mesh: meshes.add(Mesh::from(shape::Box::new(0.03, POLE_HEIGHT, 0.03))),
+            material: materials.add(Color::rgb(0.3, 0.3, 0.3).into()),
+            transform: Transform::from_translation(pole + Vec3::Y * POLE_HEIGHT / 2.0),
+            ..default()
+        },
+        NetworkEntity,
+    ));
+    commands.spawn((
+        PbrBundle {
+            mesh: meshes.add(Mesh::from(shape::Box::new(0.1, 0.1, 0.1))),
+            material: signal_materials.for_state(state),
+            transform: Transform::from_translation(pole + Vec3::Y * POLE_HEIGHT),
+            ..default()
+        },
+        SignalHead { node: node_id },
+        Name::new(format!("Signal head {node_id}")),
+        NetworkEntity,
+    ));
 }
@@ -0,0 +1,198 @@
+use std::collections::VecDeque;
+
+use bevy::prelude::*;
+use bevy_inspector_egui::{bevy_egui::EguiContexts, egui};
+use traffic_simulator::{Simulator, Snapshot, TIME_EPSILON};
+
+use crate::{editor::editing, ResSim, SimulationLoaded};
+
+/// Simulated time between two snapshots
+const SNAPSHOT_INTERVAL: f32 = 1.0; // s
+/// The oldest snapshots are dropped after this, ten minutes with the interval above
+const MAX_SNAPSHOTS: usize = 600;
+
+/// Pauses, speeds up and steps the simulation, and goes back in time to snapshots of it
+pub struct PlaybackPlugin;
+
+impl Plugin for PlaybackPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Playback>()
+            .add_system(
+                tick_simulation
+                    .run_if(not(editing))
+                    .in_schedule(CoreSchedule::FixedUpdate),
+            )
+            .add_system(clear_history)
+            .add_system(playback_shortcuts)
+            .add_system(playback_panel.run_if(not(editing)));
+    }
+}
+
+#[derive(Resource)]
+pub struct Playback {
+    paused: bool,
+    /// Ticks per fixed time step, the time step of the simulation itself doesn't change
+    speed: f32,
+    /// Whole ticks are run, the fraction left over is saved for the next step
+    pending_ticks: f32,
+    step_requested: bool,
+    /// Oldest first
+    history: VecDeque<Snapshot>,
+    /// The snapshot that was restored, if the simulation went back in time
+    viewing: Option<usize>,
+    /// The simulation from before it went back in time. Its reports cover all snapshots,
+    /// so any of them can be restored from it.
+    latest: Option<Simulator>,
+}
+
+impl Default for Playback {
+    fn default() -> Self {
+        Self {
+            paused: false,
+            speed: 1.0,
+            pending_ticks: 0.0,
+            step_requested: false,
+            history: VecDeque::new(),
+            viewing: None,
+            latest: None,
+        }
+    }
+}
+
+impl Playback {
+    /// Continuing from a restored snapshot makes a new future, so the later ones are dropped
+    fn leave_history(&mut self) {
+        if let Some(index) = self.viewing.take() {
+            self.history.truncate(index + 1);
+        }
+        self.latest = None;
+    }
+
+    fn record(&mut self, simulator: &Simulator) {
+        let is_due = self.history.back().is_none_or(|last| {
+            simulator.current_time() - last.current_time() >= SNAPSHOT_INTERVAL - TIME_EPSILON
+        });
+        if is_due {
+            self.history.push_back(simulator.snapshot());
+            if self.history.len() > MAX_SNAPSHOTS {
+                self.history.pop_front();
+            }
+        }
+    }
+}
+
+fn tick_simulation(
+    mut simulator: ResMut<ResSim>,
+    mut playback: ResMut<Playback>,
+    fixed_time: Res<FixedTime>,
+) {
+    let ticks = if playback.paused {
+        if !playback.step_requested {
+            return;
+        }
+        playback.step_requested = false;
+        1
+    } else {
+        playback.pending_ticks += playback.speed;
+        let ticks = playback.pending_ticks.floor();
+        playback.pending_ticks -= ticks;
+        ticks as u32
+    };
+    if ticks == 0 {
+        return;
+    }
+
+    playback.leave_history();
+    for _ in 0..ticks {
+        playback.record(&simulator.0);
+        simulator.0.tick(fixed_time.period.as_secs_f32());
+    }
+}
+
+/// Snapshots of another simulation don't apply anymore
+fn clear_history(mut loaded: EventReader<SimulationLoaded>, mut playback: ResMut<Playback>) {
+    if !loaded.is_empty() {
+        loaded.clear();
+        playback.history.clear();
+        playback.viewing = None;
+        playback.latest = None;
+    }
+}
+
+fn playback_shortcuts(
+    keys: Res<Input<KeyCode>>,
+    mut contexts: EguiContexts,
+    mut playback: ResMut<Playback>,
+) {
+    if contexts.ctx_mut().wants_keyboard_input() {
+        return;
+    }
+
+    if keys.just_pressed(KeyCode::Space) {
+        playback.paused = !playback.paused;
+    }
+    if keys.just_pressed(KeyCode::Period) {
+        playback.paused = true;
+        playback.step_requested = true;
+    }
+}
+
+fn playback_panel(
+    mut contexts: EguiContexts,
+    mut playback: ResMut<Playback>,
+    mut simulator: ResMut<ResSim>,
+) {
+    egui::Window::new("Playback")
+        .anchor(egui::Align2::LEFT_BOTTOM, egui::vec2(10.0, -10.0))
+        .resizable(false)
+        .show(contexts.ctx_mut(), |ui| {
+            ui.label(format!("Time {:.1} s", simulator.0.current_time()));
+
+            ui.horizontal(|ui| {
+                let label = if playback.paused { "Play" } else { "Pause" };
+                if ui.button(label).clicked() {
+                    playback.paused = !playback.paused;
+                }
+                if ui
+                    .add_enabled(playback.paused, egui::Button::new("Step"))
+                    .clicked()
+                {
+                    playback.step_requested = true;
+                }
+            });
+
+            ui.add(
+                egui::Slider::new(&mut playback.speed, 0.1..=16.0)
+                    .logarithmic(true)
+                    .suffix("x")
+                    .text("Speed"),
+            );
+
+            if playback.history.is_empty() {
+                return;
+            }
+            let last = playback.history.len() - 1;
+            let mut index = playback.viewing.unwrap_or(last);
+            let response = ui.add(
+                egui::Slider::new(&mut index, 0..=last)
+                    .show_value(false)
+                    .text("History"),
+            );
+            if response.changed() {
+                // Going back in time pauses, so there's time to look around
+                playback.paused = true;
+                playback.viewing = Some(index);
+                let latest = playback
+                    .latest
+                    .get_or_insert_with(|| simulator.0.clone())
+                    .clone();
+                simulator.0 = latest;
+                simulator.0.restore(&playback.history[index]);
+            }
+            ui.label(format!(
+                "Snapshots from {:.0} s to {:.0} s",
+                playback.history[0].current_time(),
+                playback.history[last].current_time()
+            ));
+        });
+}
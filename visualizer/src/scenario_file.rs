@@ -0,0 +1,130 @@
+use std::{
+    path::PathBuf,
+    time::{Duration, SystemTime},
+};
+
+use bevy::prelude::*;
+use bevy_inspector_egui::{bevy_egui::EguiContexts, egui};
+use traffic_simulator::scenario::Scenario;
+
+use crate::{LoadedScenario, ResSim, SimulationLoaded};
+
+/// How often the file is checked for changes
+const POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Loads the simulation from a scenario file and loads it again when the file changes.
+/// Without a path the simulation stays as it is.
+pub struct ScenarioFilePlugin {
+    pub path: Option<PathBuf>,
+}
+
+impl Plugin for ScenarioFilePlugin {
+    fn build(&self, app: &mut App) {
+        if let Some(path) = &self.path {
+            app.insert_resource(ScenarioFile::new(path.clone()))
+                .add_startup_system(watch_scenario_file)
+                .add_system(watch_scenario_file);
+        }
+        app.add_system(show_scenario_status);
+    }
+}
+
+#[derive(Resource)]
+pub struct ScenarioFile {
+    path: PathBuf,
+    /// When the loaded version of the file was written
+    modified: Option<SystemTime>,
+    /// Why the last version couldn't be loaded. The simulation of the version before that
+    /// keeps running.
+    error: Option<String>,
+    poll_timer: Timer,
+}
+
+impl ScenarioFile {
+    pub fn new(path: PathBuf) -> Self {
+        Self {
+            path,
+            modified: None,
+            error: None,
+            poll_timer: Timer::new(POLL_INTERVAL, TimerMode::Repeating),
+        }
+    }
+
+    pub fn path(&self) -> &PathBuf {
+        &self.path
+    }
+
+    /// Loads the scenario if the file was written since the last time
+    fn reload(&mut self) -> Option<Scenario> {
+        let modified = match std::fs::metadata(&self.path).and_then(|file| file.modified()) {
+            Ok(modified) => modified,
+            Err(error) => {
+                self.modified = None;
+                self.error = Some(format!("could not read the file: {error}"));
+                return None;
+            }
+        };
+        if self.modified == Some(modified) {
+            return None;
+        }
+        self.modified = Some(modified);
+
+        match Scenario::load(&self.path) {
+            Ok(scenario) => {
+                self.error = None;
+                Some(scenario)
+            }
+            Err(error) => {
+                self.error = Some(error.to_string());
+                None
+            }
+        }
+    }
+}
+
+fn watch_scenario_file(
+    time: Res<Time>,
+    mut file: ResMut<ScenarioFile>,
+    mut simulator: ResMut<ResSim>,
+    mut scenario: ResMut<LoadedScenario>,
+    mut loaded: EventWriter<SimulationLoaded>,
+) {
+    // The first time is at startup, when nothing has been loaded yet
+    let first_load = file.modified.is_none() && file.error.is_none();
+    if !file.poll_timer.tick(time.delta()).just_finished() && !first_load {
+        return;
+    }
+
+    if let Some(new_scenario) = file.reload() {
+        simulator.0 = new_scenario.clone().into_simulator();
+        scenario.0 = new_scenario;
+        loaded.send(SimulationLoaded);
+    }
+}
+
+fn show_scenario_status(mut contexts: EguiContexts, file: Option<Res<ScenarioFile>>) {
+    egui::Window::new("Scenario")
+        .anchor(egui::Align2::RIGHT_TOP, egui::vec2(-10.0, 10.0))
+        .resizable(false)
+        .show(contexts.ctx_mut(), |ui| {
+            match file {
+                Some(file) => {
+                    ui.label(file.path().display().to_string());
+                    match &file.error {
+                        Some(error) => {
+                            ui.colored_label(egui::Color32::RED, error);
+                            ui.label("The simulation keeps running as it was");
+                        }
+                        None => {
+                            ui.label("Loaded, reloads when the file changes");
+                        }
+                    }
+                }
+                None => {
+                    ui.label("Built-in demo network");
+                    ui.label("Pass the path of a scenario file to load it");
+                }
+            }
+            ui.label("Press E to edit the scenario");
+        });
+}